@@ -0,0 +1,40 @@
+use std::io::Write;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use packbed::{packbed_with, PackConfig};
+use tempfile::NamedTempFile;
+
+// transcripts overlapping their neighbours in small clusters on one
+// chromosome, so most of the time goes into merging components
+fn synthetic(n: u64) -> NamedTempFile {
+    let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+    for i in 0..n {
+        let start = (i / 8) * 10_000 + (i % 8) * 100;
+        writeln!(
+            file,
+            "chr1\t{}\t{}\ttx{}\t0\t+\t{}\t{}\t0,0,0\t2\t200,200,\t0,800,",
+            start,
+            start + 1_000,
+            i,
+            start,
+            start + 1_000
+        )
+        .unwrap();
+    }
+    file
+}
+
+fn bench_pack(c: &mut Criterion) {
+    let file = synthetic(500_000);
+    let config = PackConfig::new(false, true, false);
+
+    let mut group = c.benchmark_group("pack");
+    group.sample_size(10);
+    group.bench_function("packbed_with 500k transcripts", |b| {
+        b.iter(|| packbed_with(vec![file.path()], &config).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_pack);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use packbed::record::parse_u64;
+use packbed::Bed12;
+
+fn synthetic(n: u64) -> Vec<String> {
+    (0..n)
+        .map(|i| {
+            let start = i * 1_000 + 10_000_000;
+            format!(
+                "chr1\t{}\t{}\ttx{}\t0\t+\t{}\t{}\t0,0,0\t3\t100,200,300,\t0,400,700,",
+                start,
+                start + 1_000,
+                i,
+                start + 50,
+                start + 950
+            )
+        })
+        .collect()
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let lines = synthetic(100_000);
+    let fields = lines
+        .iter()
+        .flat_map(|x| x.split('\t').skip(1).take(2))
+        .collect::<Vec<_>>();
+
+    c.bench_function("str::parse::<u64>", |b| {
+        b.iter(|| {
+            fields
+                .iter()
+                .map(|x| x.parse::<u64>().unwrap())
+                .fold(0u64, |acc, x| acc.wrapping_add(black_box(x)))
+        })
+    });
+
+    c.bench_function("parse_u64", |b| {
+        b.iter(|| {
+            fields
+                .iter()
+                .map(|x| parse_u64(x).unwrap())
+                .fold(0u64, |acc, x| acc.wrapping_add(black_box(x)))
+        })
+    });
+
+    c.bench_function("Bed12::parse", |b| {
+        b.iter(|| {
+            lines
+                .iter()
+                .filter_map(|x| Bed12::parse(black_box(x), false).ok())
+                .count()
+        })
+    });
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
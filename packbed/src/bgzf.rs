@@ -0,0 +1,153 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::MultiGzDecoder;
+use memmap2::Mmap;
+
+use crate::reader;
+
+fn gzi_path<P: AsRef<Path>>(file: P) -> PathBuf {
+    let mut gzi = file.as_ref().as_os_str().to_owned();
+    gzi.push(".gzi");
+    PathBuf::from(gzi)
+}
+
+// bgzip .gzi: little-endian u64 entry count followed by (compressed,
+// uncompressed) u64 offset pairs; the implicit first block at 0 is added
+pub fn read_gzi<P: AsRef<Path> + Debug>(file: P) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+    let mut bytes = Vec::new();
+    File::open(file)?.read_to_end(&mut bytes)?;
+
+    let word = |i: usize| -> Result<u64, Box<dyn std::error::Error>> {
+        let chunk = bytes
+            .get(i * 8..i * 8 + 8)
+            .ok_or("ERROR: truncated .gzi index")?;
+        Ok(u64::from_le_bytes(chunk.try_into()?))
+    };
+
+    let n = word(0)? as usize;
+    let mut blocks = vec![0];
+    for i in 0..n {
+        blocks.push(word(1 + 2 * i)?);
+    }
+
+    Ok(blocks)
+}
+
+// chromosome of the first line starting inside the block at offset
+fn first_chrom(data: &[u8], offset: u64) -> Option<String> {
+    let mut lines = BufReader::new(MultiGzDecoder::new(&data[offset as usize..])).lines();
+    if offset > 0 {
+        lines.next()?.ok()?;
+    }
+
+    let line = lines.next()?.ok()?;
+    line.split('\t').next().map(String::from)
+}
+
+// records of one chromosome from a bgzipped BED sorted by chromosome
+// name (sort -k1,1 -k2,2n); with a .gzi next to the file only the blocks
+// holding chrom are decompressed, otherwise the whole file is read
+pub fn read_chrom<P: AsRef<Path> + Debug>(
+    file: P,
+    chrom: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let gzi = gzi_path(&file);
+    if !gzi.exists() {
+        let contents = reader(&file)?;
+        return Ok(contents
+            .lines()
+            .filter(|x| x.split('\t').next() == Some(chrom))
+            .map(|x| format!("{}\n", x))
+            .collect());
+    }
+
+    let blocks = read_gzi(&gzi)?;
+    let data = unsafe { Mmap::map(&File::open(&file)?)? };
+
+    // first block whose first full line is already at or past chrom, the
+    // previous one may still hold its first records
+    let idx = blocks.partition_point(|&offset| {
+        first_chrom(&data, offset).is_some_and(|chr| chr.as_str() < chrom)
+    });
+    let offset = blocks[idx.saturating_sub(1)];
+
+    let mut lines = BufReader::new(MultiGzDecoder::new(&data[offset as usize..])).lines();
+    if offset > 0 {
+        lines.next();
+    }
+
+    let mut contents = String::new();
+    for line in lines {
+        let line = line?;
+        match line.split('\t').next() {
+            Some(chr) if chr == chrom => {
+                contents.push_str(&line);
+                contents.push('\n');
+            }
+            Some(chr) if chr > chrom && !line.starts_with('#') => break,
+            _ => {}
+        }
+    }
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_read_chrom_with_gzi() {
+        let line = |chr: &str, i: usize| {
+            format!(
+                "{}\t{}\t{}\tread{}\t0\t+\t{}\t{}\t0\t1\t100,\t0,\n",
+                chr,
+                i * 100,
+                i * 100 + 100,
+                i,
+                i * 100,
+                i * 100 + 100
+            )
+        };
+        let text = (0..30)
+            .map(|i| line(["s1", "s2", "s3"][i / 10], i))
+            .collect::<String>();
+
+        // one gzip member per 200 bytes, cutting lines like bgzip does
+        let file = NamedTempFile::with_suffix(".bed.gz").unwrap();
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        for chunk in text.as_bytes().chunks(200) {
+            if !data.is_empty() {
+                offsets.push(data.len() as u64);
+            }
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(chunk).unwrap();
+            data.extend(encoder.finish().unwrap());
+        }
+        std::fs::write(file.path(), &data).unwrap();
+
+        let mut gzi = (offsets.len() as u64).to_le_bytes().to_vec();
+        for (i, offset) in offsets.iter().enumerate() {
+            gzi.extend(offset.to_le_bytes());
+            gzi.extend(((i as u64 + 1) * 200).to_le_bytes());
+        }
+        let gzi_file = gzi_path(file.path());
+        std::fs::write(&gzi_file, gzi).unwrap();
+
+        let expected = (10..20).map(|i| line("s2", i)).collect::<String>();
+        assert_eq!(read_chrom(file.path(), "s2").unwrap(), expected);
+        assert_eq!(read_chrom(file.path(), "s1").unwrap().lines().count(), 10);
+        assert!(read_chrom(file.path(), "s4").unwrap().is_empty());
+
+        std::fs::remove_file(&gzi_file).unwrap();
+        assert_eq!(read_chrom(file.path(), "s2").unwrap(), expected);
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{Buckets, GenePred};
+
+// exonic depth over records as (start, end, depth) runs, zero-depth gaps
+// are skipped and adjacent runs of equal depth merged
+pub fn exon_coverage(records: &[&GenePred]) -> Vec<(u64, u64, u32)> {
+    let mut events = records
+        .iter()
+        .flat_map(|x| &x.exons)
+        .flat_map(|&(start, end)| [(start, 1i64), (end, -1i64)])
+        .collect::<Vec<_>>();
+    events.sort_unstable();
+
+    let mut runs: Vec<(u64, u64, u32)> = Vec::new();
+    let mut depth = 0i64;
+    let mut last = 0;
+
+    for (pos, delta) in events {
+        if depth > 0 && pos > last {
+            match runs.last_mut() {
+                Some(run) if run.1 == last && run.2 == depth as u32 => run.1 = pos,
+                _ => runs.push((last, pos, depth as u32)),
+            }
+        }
+        depth += delta;
+        last = pos;
+    }
+
+    runs
+}
+
+// a single bedGraph at output, or with stranded one per strand next to it
+// as <stem>.plus.<ext> and <stem>.minus.<ext>; returns the paths written
+pub fn coverage_bedgraph<P: AsRef<Path> + Debug>(
+    output: P,
+    buckets: &Buckets,
+    stranded: bool,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let output = output.as_ref();
+
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    let tracks = if stranded {
+        let ext = output
+            .extension()
+            .map_or("bedgraph".to_string(), |x| x.to_string_lossy().to_string());
+        vec![
+            (output.with_extension(format!("plus.{}", ext)), Some('+')),
+            (output.with_extension(format!("minus.{}", ext)), Some('-')),
+        ]
+    } else {
+        vec![(output.to_path_buf(), None)]
+    };
+
+    for (path, strand) in &tracks {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        for chr in &chroms {
+            let Some(comps) = buckets.get(chr) else {
+                continue;
+            };
+            let records = comps
+                .iter()
+                .flatten()
+                .filter(|x| strand.is_none_or(|s| x.strand == s))
+                .collect::<Vec<_>>();
+
+            for (start, end, depth) in exon_coverage(&records) {
+                writeln!(file, "{}\t{}\t{}\t{}", chr, start, end, depth)?;
+            }
+        }
+    }
+
+    Ok(tracks.into_iter().map(|(path, _)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packbed;
+    use tempfile::{NamedTempFile, TempDir};
+
+    #[test]
+    fn test_stranded_coverage_splits_strands() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t100\t200\t0\t1\t100,\t0,\ns8\t150\t250\tread2\t0\t-\t150\t250\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let dir = TempDir::new().unwrap();
+
+        let unstranded =
+            coverage_bedgraph(dir.path().join("cov.bedgraph"), &buckets, false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&unstranded[0]).unwrap(),
+            "s8\t100\t150\t1\ns8\t150\t200\t2\ns8\t200\t250\t1\n"
+        );
+
+        let stranded = coverage_bedgraph(dir.path().join("cov.bedgraph"), &buckets, true).unwrap();
+        assert_eq!(stranded[0], dir.path().join("cov.plus.bedgraph"));
+        assert_eq!(
+            std::fs::read_to_string(&stranded[0]).unwrap(),
+            "s8\t100\t200\t1\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&stranded[1]).unwrap(),
+            "s8\t150\t250\t1\n"
+        );
+    }
+}
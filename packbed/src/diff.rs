@@ -0,0 +1,167 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use hashbrown::HashSet;
+use rayon::prelude::*;
+
+use crate::index::ChromIndex;
+use crate::{binreader, component_span, Buckets, GenePred};
+
+pub type ComponentDiff = (Vec<Vec<GenePred>>, Vec<Vec<GenePred>>, Vec<Vec<GenePred>>);
+
+// content hash over member names and structure, independent of member
+// order and of the colors assigned by colorize
+pub fn component_hash(component: &[GenePred]) -> u64 {
+    let mut members = component
+        .iter()
+        .map(|x| (&x.name, x.strand, (x.start, x.end), &x.exons))
+        .collect::<Vec<_>>();
+    members.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    members.hash(&mut hasher);
+    hasher.finish()
+}
+
+// new components are matched to old ones by span overlap on the same
+// chromosome: no match is added, a match without an identical hash is
+// modified (reported in its new version) and unmatched old ones are removed
+pub fn diff_components<P: AsRef<Path> + Debug>(
+    old_bin: P,
+    new_buckets: &Buckets,
+) -> Result<ComponentDiff, Box<dyn std::error::Error>> {
+    let old = binreader(old_bin)?
+        .into_par_iter()
+        .map(|(chr, comps)| (chr, ChromIndex::new(comps)))
+        .collect::<hashbrown::HashMap<_, _>>();
+
+    let mut chroms = old
+        .keys()
+        .cloned()
+        .chain(new_buckets.iter().map(|x| x.key().clone()))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    let per_chrom = chroms
+        .par_iter()
+        .map(|chr| {
+            let empty = ChromIndex::default();
+            let old_index = old.get(chr).unwrap_or(&empty);
+            let old_comps = &old_index.components;
+            let new_comps = new_buckets
+                .get(chr)
+                .map(|x| x.value().clone())
+                .unwrap_or_default();
+
+            let old_hashes = old_comps
+                .iter()
+                .map(|x| component_hash(x))
+                .collect::<Vec<_>>();
+            let mut matched = vec![false; old_comps.len()];
+            let (mut added, mut modified) = (Vec::new(), Vec::new());
+
+            for comp in new_comps {
+                let (start, end) = component_span(&comp);
+                let hash = component_hash(&comp);
+                let hits = old_index.overlapping(start, end);
+
+                for &i in &hits {
+                    matched[i] = true;
+                }
+
+                if hits.is_empty() {
+                    added.push(comp);
+                } else if !hits.iter().any(|&i| old_hashes[i] == hash) {
+                    modified.push(comp);
+                }
+            }
+
+            let removed = old_comps
+                .iter()
+                .zip(matched)
+                .filter(|(_, m)| !m)
+                .map(|(x, _)| x.clone())
+                .collect::<Vec<_>>();
+
+            (added, removed, modified)
+        })
+        .collect::<Vec<_>>();
+
+    let mut diff: ComponentDiff = (Vec::new(), Vec::new(), Vec::new());
+    for (added, removed, modified) in per_chrom {
+        diff.0.extend(added);
+        diff.1.extend(removed);
+        diff.2.extend(modified);
+    }
+
+    Ok(diff)
+}
+
+// added.bed, removed.bed and modified.bed inside output
+pub fn diffwriter<T: AsRef<Path> + Debug>(
+    diff: &ComponentDiff,
+    output: T,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(&output)?;
+
+    for (name, comps) in [
+        ("added", &diff.0),
+        ("removed", &diff.1),
+        ("modified", &diff.2),
+    ] {
+        let path = output.as_ref().join(format!("{}.bed", name));
+        let mut file = BufWriter::new(File::create(path)?);
+
+        for tx in comps.iter().flatten() {
+            writeln!(file, "{}", tx.line())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binwriter, packbed};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_diff_components_unchanged_and_modified() {
+        let mut old = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            old,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t1000\t1200\tread2\t0\t+\t1010\t1190\t0\t1\t200,\t0,"
+        )
+        .unwrap();
+
+        let mut new = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            new,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t1000\t1200\tread2\t0\t+\t1010\t1190\t0\t1\t200,\t0,\ns8\t1100\t1300\tread3\t0\t+\t1110\t1290\t0\t1\t200,\t0,\ns9\t100\t200\tread4\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let bin = NamedTempFile::with_suffix(".bin").unwrap();
+        binwriter(
+            bin.path(),
+            packbed(vec![old.path()], false, false, true).unwrap(),
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![new.path()], false, false, false).unwrap();
+        let (added, removed, modified) = diff_components(bin.path(), &buckets).unwrap();
+
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0][0].name, "read4");
+        assert!(removed.is_empty());
+        assert_eq!(modified.len(), 1);
+        assert_eq!(modified[0].len(), 2);
+    }
+}
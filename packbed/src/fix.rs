@@ -0,0 +1,194 @@
+use std::fmt::{self, Debug, Display};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::reader;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Fix {
+    TrimmedCarriageReturn,
+    DroppedZeroLengthExons(usize),
+    SortedBlocks,
+    CollapsedBookEndedExons(usize),
+    NormalizedRgb,
+}
+
+impl Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::TrimmedCarriageReturn => write!(f, "trimmed_cr"),
+            Fix::DroppedZeroLengthExons(n) => write!(f, "dropped_zero_length_exons:{}", n),
+            Fix::SortedBlocks => write!(f, "sorted_blocks"),
+            Fix::CollapsedBookEndedExons(n) => write!(f, "collapsed_book_ended_exons:{}", n),
+            Fix::NormalizedRgb => write!(f, "normalized_rgb"),
+        }
+    }
+}
+
+// safe textual corrections over a BED12 line; lines that do not carry
+// 12 columns are only trimmed and left for the parser to reject
+pub fn fix_line(line: &str) -> (String, Vec<Fix>) {
+    let mut fixes = Vec::new();
+
+    let trimmed = line.trim_end_matches('\r');
+    if trimmed.len() != line.len() {
+        fixes.push(Fix::TrimmedCarriageReturn);
+    }
+
+    let mut fields = trimmed.split('\t').map(String::from).collect::<Vec<_>>();
+    if fields.len() < 12 {
+        return (trimmed.to_string(), fixes);
+    }
+
+    if fields[8] == "0" {
+        fields[8] = "0,0,0".to_string();
+        fixes.push(Fix::NormalizedRgb);
+    }
+
+    let group = |field: &str| {
+        field
+            .split(',')
+            .filter(|x| !x.is_empty())
+            .map(|x| x.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    let (Ok(sizes), Ok(starts)) = (group(&fields[10]), group(&fields[11])) else {
+        return (fields.join("\t"), fixes);
+    };
+    if sizes.len() != starts.len() {
+        return (fields.join("\t"), fixes);
+    }
+
+    let mut blocks = starts.into_iter().zip(sizes).collect::<Vec<_>>();
+
+    let n = blocks.len();
+    blocks.retain(|&(_, size)| size > 0);
+    if blocks.len() != n {
+        fixes.push(Fix::DroppedZeroLengthExons(n - blocks.len()));
+    }
+
+    if blocks.windows(2).any(|w| w[0].0 > w[1].0) {
+        blocks.sort_unstable();
+        fixes.push(Fix::SortedBlocks);
+    }
+
+    let n = blocks.len();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(n);
+    for (start, size) in blocks {
+        match merged.last_mut() {
+            Some(prev) if prev.0 + prev.1 == start => prev.1 += size,
+            _ => merged.push((start, size)),
+        }
+    }
+    if merged.len() != n {
+        fixes.push(Fix::CollapsedBookEndedExons(n - merged.len()));
+    }
+
+    fields[9] = merged.len().to_string();
+    fields[10] = merged.iter().map(|(_, z)| format!("{},", z)).collect();
+    fields[11] = merged.iter().map(|(s, _)| format!("{},", s)).collect();
+
+    (fields.join("\t"), fixes)
+}
+
+// writes a corrected copy of the inputs and a report of the fixes applied
+// per file and 1-based line; returns the number of lines changed
+pub fn fix_bed<P: AsRef<Path> + Debug, Q: AsRef<Path> + Debug>(
+    files: Vec<P>,
+    output: Q,
+    report: Q,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut out = BufWriter::new(File::create(output)?);
+    let mut rep = BufWriter::new(File::create(report)?);
+    let mut changed = 0;
+
+    writeln!(rep, "file\tline\tfixes")?;
+    for file in files {
+        let contents = reader(&file)?;
+
+        // split by hand, lines() would already drop the \r we want to report
+        for (i, line) in contents.split_terminator('\n').enumerate() {
+            if line.starts_with("#") || line.trim_end_matches('\r').is_empty() {
+                writeln!(out, "{}", line)?;
+                continue;
+            }
+
+            let (fixed, fixes) = fix_line(line);
+            writeln!(out, "{}", fixed)?;
+
+            if !fixes.is_empty() {
+                changed += 1;
+                writeln!(
+                    rep,
+                    "{}\t{}\t{}",
+                    file.as_ref().display(),
+                    i + 1,
+                    fixes
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )?;
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bed12;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_fix_bed_each_defect() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t100\t200\t0,0,0\t2\t20,20,\t0,80,\r\n\
+             s8\t100\t200\tread2\t0\t+\t100\t200\t0,0,0\t3\t20,0,20,\t0,40,80,\n\
+             s8\t100\t200\tread3\t0\t+\t100\t200\t0,0,0\t2\t20,20,\t80,0,\n\
+             s8\t100\t200\tread4\t0\t+\t100\t200\t0,0,0\t3\t20,30,20,\t0,20,80,\n\
+             s8\t100\t200\tread5\t0\t+\t100\t200\t0\t2\t20,20,\t0,80,\n"
+        )
+        .unwrap();
+
+        let output = NamedTempFile::with_suffix(".bed").unwrap();
+        let report = NamedTempFile::with_suffix(".tsv").unwrap();
+        let changed = fix_bed(vec![file.path()], output.path(), report.path()).unwrap();
+        assert_eq!(changed, 5);
+
+        let fixed = std::fs::read_to_string(output.path()).unwrap();
+        let records = fixed
+            .lines()
+            .map(|x| Bed12::parse(x, false).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(records[0].exons, vec![(100, 120), (180, 200)]);
+        assert_eq!(records[1].exon_count, 2);
+        assert_eq!(records[2].exons, vec![(100, 120), (180, 200)]);
+        assert_eq!(records[3].exons, vec![(100, 150), (180, 200)]);
+        assert_eq!(records[4].rgb, "0,0,0");
+
+        let report = std::fs::read_to_string(report.path()).unwrap();
+        let fixes = report
+            .lines()
+            .skip(1)
+            .map(|x| x.split('\t').nth(2).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fixes,
+            vec![
+                "trimmed_cr",
+                "dropped_zero_length_exons:1",
+                "sorted_blocks",
+                "collapsed_book_ended_exons:1",
+                "normalized_rgb"
+            ]
+        );
+    }
+}
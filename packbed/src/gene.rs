@@ -0,0 +1,98 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use hashbrown::HashMap;
+use rayon::prelude::*;
+
+use crate::{GenePred, GenePredMap};
+
+// name-based grouping, independent of overlap: GENE.isoform names go to
+// GENE, names without the separator are their own gene
+pub fn group_by_gene(tracks: &GenePredMap, sep: char) -> HashMap<String, Vec<GenePred>> {
+    let mut genes = tracks
+        .par_iter()
+        .flat_map(|(_, transcripts)| transcripts.par_iter())
+        .fold(
+            HashMap::new,
+            |mut acc: HashMap<String, Vec<GenePred>>, tx| {
+                let gene = tx
+                    .name
+                    .rsplit_once(sep)
+                    .map_or(tx.name.as_str(), |(gene, _)| gene);
+                acc.entry(gene.to_string()).or_default().push(tx.clone());
+                acc
+            },
+        )
+        .reduce(HashMap::new, |mut acc, map| {
+            for (k, v) in map {
+                acc.entry(k).or_default().extend(v);
+            }
+            acc
+        });
+
+    genes.par_iter_mut().for_each(|(_, v)| {
+        v.par_sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    });
+
+    genes
+}
+
+// one {gene}.bed per gene inside output
+pub fn genewriter<T: AsRef<Path> + Debug + Sync>(
+    genes: &HashMap<String, Vec<GenePred>>,
+    output: T,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(&output)?;
+
+    genes
+        .par_iter()
+        .try_for_each(|(gene, transcripts)| -> std::io::Result<()> {
+            let filename = format!("{}/{}.bed", output.as_ref().display(), gene);
+            let mut file = BufWriter::new(File::create(&filename)?);
+
+            for x in transcripts {
+                writeln!(file, "{}", x.line())?;
+            }
+            file.flush()
+        })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bed12;
+
+    #[test]
+    fn test_group_by_gene_two_genes() {
+        let mut tracks = GenePredMap::new();
+        for line in [
+            "s8\t100\t200\tGENEA.1\t0\t+\t100\t200\t0\t1\t100,\t0,",
+            "s9\t5000\t6000\tGENEA.2\t0\t+\t5000\t6000\t0\t1\t1000,\t0,",
+            "s8\t150\t250\tGENEB.1\t0\t+\t150\t250\t0\t1\t100,\t0,",
+        ] {
+            let record = Bed12::parse(line, false).unwrap();
+            tracks.entry(record.chrom.clone()).or_default().push(record);
+        }
+
+        let genes = group_by_gene(&tracks, '.');
+
+        assert_eq!(genes.len(), 2);
+        assert_eq!(genes["GENEA"].len(), 2);
+        assert_eq!(genes["GENEA"][0].name, "GENEA.1");
+        assert_eq!(genes["GENEB"].len(), 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        genewriter(&genes, dir.path()).unwrap();
+        assert!(dir.path().join("GENEA.bed").exists());
+        assert!(dir.path().join("GENEB.bed").exists());
+
+        // a name pointing into a missing directory fails the write
+        let mut genes = genes;
+        genes.insert("missing/GENEC".to_string(), genes["GENEB"].clone());
+        assert!(genewriter(&genes, dir.path()).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{merge_component, Buckets, GenePred};
+
+// CDS pieces of a transcript with their phase, counted from the start codon
+fn cds_phases(tx: &GenePred) -> Vec<(u64, u64, u64)> {
+    let (cds_start, cds_end) = (tx.cds_start, tx.cds_end);
+    if cds_start == cds_end {
+        return Vec::new();
+    }
+
+    let mut pieces = tx
+        .exons
+        .iter()
+        .filter_map(|&(s, e)| {
+            let (s, e) = (s.max(cds_start), e.min(cds_end));
+            (s < e).then_some((s, e))
+        })
+        .collect::<Vec<_>>();
+    if tx.strand == '-' {
+        pieces.reverse();
+    }
+
+    let mut done = 0;
+    let mut phased = pieces
+        .into_iter()
+        .map(|(s, e)| {
+            let phase = (3 - done % 3) % 3;
+            done += e - s;
+            (s, e, phase)
+        })
+        .collect::<Vec<_>>();
+    phased.sort_unstable();
+
+    phased
+}
+
+// one gene per component spanning its merged model, members as mRNA
+// children with their own exon and CDS features; coordinates are 1-based
+pub fn gff3writer<P: AsRef<Path> + Debug>(
+    file: P,
+    buckets: &Buckets,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+    writeln!(file, "##gff-version 3")?;
+
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    let mut gene = 0;
+    for chr in chroms {
+        let Some(comps) = buckets.get(&chr) else {
+            continue;
+        };
+
+        for (i, comp) in comps.iter().enumerate().filter(|(_, x)| !x.is_empty()) {
+            gene += 1;
+            let model = merge_component(comp)?;
+            let (start, end) = (model.start, model.end);
+            writeln!(
+                file,
+                "{}\tpackbed\tgene\t{}\t{}\t.\t{}\t.\tID=gene{};Name={}_{}",
+                chr,
+                start + 1,
+                end,
+                model.strand,
+                gene,
+                chr,
+                i
+            )?;
+
+            for tx in comp {
+                let (start, end) = (tx.start, tx.end);
+                writeln!(
+                    file,
+                    "{}\tpackbed\tmRNA\t{}\t{}\t.\t{}\t.\tID={};Parent=gene{}",
+                    chr,
+                    start + 1,
+                    end,
+                    tx.strand,
+                    tx.name,
+                    gene
+                )?;
+
+                for (k, &(s, e)) in tx.exons.iter().enumerate() {
+                    writeln!(
+                        file,
+                        "{}\tpackbed\texon\t{}\t{}\t.\t{}\t.\tID=exon:{}:{};Parent={}",
+                        chr,
+                        s + 1,
+                        e,
+                        tx.strand,
+                        tx.name,
+                        k + 1,
+                        tx.name
+                    )?;
+                }
+
+                for (s, e, phase) in cds_phases(tx) {
+                    writeln!(
+                        file,
+                        "{}\tpackbed\tCDS\t{}\t{}\t.\t{}\t{}\tID=cds:{};Parent={}",
+                        chr,
+                        s + 1,
+                        e,
+                        tx.strand,
+                        phase,
+                        tx.name,
+                        tx.name
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packbed;
+    use hashbrown::HashMap;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_gff3writer_hierarchy() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t400\tread1\t0\t+\t120\t380\t0\t2\t50,50,\t0,250,\ns8\t100\t500\tread2\t0\t+\t120\t480\t0\t3\t50,50,50,\t0,250,350,"
+        )
+        .unwrap();
+
+        let out = NamedTempFile::with_suffix(".gff3").unwrap();
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        gff3writer(out.path(), &buckets).unwrap();
+
+        let gff = std::fs::read_to_string(out.path()).unwrap();
+        let features = gff
+            .lines()
+            .skip(1)
+            .map(|x| {
+                let fields = x.split('\t').collect::<Vec<_>>();
+                let attrs = fields[8]
+                    .split(';')
+                    .filter_map(|a| a.split_once('='))
+                    .collect::<HashMap<_, _>>();
+                (
+                    fields[2].to_string(),
+                    fields[3].to_string(),
+                    fields[4].to_string(),
+                    attrs["ID"].to_string(),
+                    attrs.get("Parent").map(|x| x.to_string()),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let ids = features.iter().map(|x| x.3.as_str()).collect::<Vec<_>>();
+        let of = |kind: &str| features.iter().filter(|x| x.0 == kind).collect::<Vec<_>>();
+
+        let genes = of("gene");
+        assert_eq!(genes.len(), 1);
+        assert_eq!((genes[0].1.as_str(), genes[0].2.as_str()), ("101", "500"));
+        assert!(genes[0].4.is_none());
+
+        let mrnas = of("mRNA");
+        assert_eq!(mrnas.len(), 2);
+        assert!(mrnas.iter().all(|x| x.4.as_deref() == Some("gene1")));
+
+        for kind in ["exon", "CDS"] {
+            for feature in of(kind) {
+                let parent = feature.4.as_deref().unwrap();
+                assert!(ids.contains(&parent));
+                assert!(parent.starts_with("read"));
+            }
+        }
+        assert_eq!(of("exon").len(), 5);
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{component_span, Buckets};
+
+// genomic intervals covered by the spans of at least min_components
+// components of chrom, adjacent covered stretches are merged
+pub fn component_hotspots(
+    buckets: &Buckets,
+    chrom: &str,
+    min_components: usize,
+) -> Vec<(u64, u64)> {
+    let Some(comps) = buckets.get(chrom) else {
+        return Vec::new();
+    };
+
+    let mut events = comps
+        .iter()
+        .filter(|comp| !comp.is_empty())
+        .flat_map(|comp| {
+            let (start, end) = component_span(comp);
+            [(start, 1i64), (end, -1i64)]
+        })
+        .collect::<Vec<_>>();
+    // spans are half-open, closing before opening at the same position
+    events.sort_unstable();
+
+    let mut hotspots: Vec<(u64, u64)> = Vec::new();
+    let mut depth = 0i64;
+    let mut open: Option<u64> = None;
+
+    for (pos, delta) in events {
+        depth += delta;
+        let hot = depth >= min_components as i64;
+
+        match (open, hot) {
+            (None, true) => open = Some(pos),
+            (Some(start), false) => {
+                if start < pos {
+                    match hotspots.last_mut() {
+                        Some(last) if last.1 == start => last.1 = pos,
+                        _ => hotspots.push((start, pos)),
+                    }
+                }
+                open = None;
+            }
+            _ => {}
+        }
+    }
+
+    hotspots
+}
+
+pub fn hotspotwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    buckets: &Buckets,
+    min_components: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    for chr in chroms {
+        for (start, end) in component_hotspots(buckets, &chr, min_components) {
+            writeln!(file, "{}\t{}\t{}", chr, start, end)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{packbed_with, PackConfig};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_component_hotspots_three_components() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t500\tread1\t0\t+\t100\t500\t0\t2\t50,50,\t0,350,\ns8\t200\t400\tread2\t0\t+\t200\t400\t0\t2\t20,20,\t0,180,\ns8\t300\t350\tread3\t0\t+\t300\t350\t0\t1\t50,\t0,"
+        )
+        .unwrap();
+
+        // exon-only overlap keeps the three nested transcripts apart
+        let buckets =
+            packbed_with(vec![file.path()], &PackConfig::new(false, true, false)).unwrap();
+        assert_eq!(buckets.get("s8").unwrap().len(), 3);
+
+        assert_eq!(component_hotspots(&buckets, "s8", 2), vec![(200, 400)]);
+        assert_eq!(component_hotspots(&buckets, "s8", 3), vec![(300, 350)]);
+        assert!(component_hotspots(&buckets, "s8", 4).is_empty());
+    }
+}
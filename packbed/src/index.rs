@@ -0,0 +1,176 @@
+use std::fmt::Debug;
+use std::path::Path;
+
+use hashbrown::HashMap;
+use rayon::prelude::*;
+
+use crate::{component_span, packbed_with, Buckets, GenePred, PackConfig};
+
+// the components of one chromosome with their spans sorted for overlap
+// lookups; empty components are kept but never found
+#[derive(Debug, Default)]
+pub(crate) struct ChromIndex {
+    // (genomic span, component index) sorted by span
+    spans: Vec<((u64, u64), usize)>,
+    // running max of span ends, lets a query stop scanning back early
+    max_end: Vec<u64>,
+    pub(crate) components: Vec<Vec<GenePred>>,
+}
+
+impl ChromIndex {
+    pub(crate) fn new(components: Vec<Vec<GenePred>>) -> Self {
+        let mut spans = components
+            .iter()
+            .enumerate()
+            .filter(|(_, comp)| !comp.is_empty())
+            .map(|(i, comp)| (component_span(comp), i))
+            .collect::<Vec<_>>();
+        spans.sort_unstable();
+
+        let mut max_end = Vec::with_capacity(spans.len());
+        for &((_, end), _) in &spans {
+            max_end.push(max_end.last().map_or(end, |&m: &u64| m.max(end)));
+        }
+
+        Self {
+            spans,
+            max_end,
+            components,
+        }
+    }
+
+    // indices of the components whose span overlaps [start, end), sorted
+    pub(crate) fn overlapping(&self, start: u64, end: u64) -> Vec<usize> {
+        let upper = self.spans.partition_point(|&((s, _), _)| s < end);
+        let mut hits = Vec::new();
+        for k in (0..upper).rev() {
+            if self.max_end[k] <= start {
+                break;
+            }
+            let ((_, e), i) = self.spans[k];
+            if e > start {
+                hits.push(i);
+            }
+        }
+        hits.sort_unstable();
+
+        hits
+    }
+}
+
+// packed components kept in memory for repeated overlap queries. The index
+// owns the components moved out of the buckets; results borrow from it and
+// live as long as the index is not dropped
+#[derive(Debug, Default)]
+pub struct ComponentIndex {
+    chroms: HashMap<String, ChromIndex>,
+}
+
+impl ComponentIndex {
+    pub fn new(buckets: Buckets) -> Self {
+        let chroms = buckets
+            .into_par_iter()
+            .map(|(chr, components)| (chr, ChromIndex::new(components)))
+            .collect();
+
+        Self { chroms }
+    }
+
+    // components whose span overlaps the half-open [start, end), as
+    // (component index, members) ordered by index
+    pub fn query(&self, chrom: &str, start: u64, end: u64) -> Vec<(usize, &[GenePred])> {
+        let Some(index) = self.chroms.get(chrom) else {
+            return Vec::new();
+        };
+
+        index
+            .overlapping(start, end)
+            .into_iter()
+            .map(|i| (i, index.components[i].as_slice()))
+            .collect()
+    }
+
+    // component whose span holds pos, the lowest index if spans nest
+    pub fn query_at(&self, chrom: &str, pos: u64) -> Option<(usize, &[GenePred])> {
+        self.query(chrom, pos, pos.saturating_add(1))
+            .into_iter()
+            .next()
+    }
+
+    pub fn get(&self, chrom: &str, index: usize) -> Option<&[GenePred]> {
+        self.chroms
+            .get(chrom)?
+            .components
+            .get(index)
+            .map(|x| x.as_slice())
+    }
+
+    pub fn len(&self) -> usize {
+        self.chroms.values().map(|x| x.components.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// packs and indexes in one go, nothing is written to disk
+pub fn packbed_index<T: AsRef<Path> + Debug + Send + Sync>(
+    bed: Vec<T>,
+    config: &PackConfig,
+) -> Result<ComponentIndex, anyhow::Error> {
+    Ok(ComponentIndex::new(packbed_with(bed, config)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_packbed_index_point_query() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t500\t600\tread2\t0\t-\t510\t590\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let index =
+            packbed_index(vec![file.path()], &PackConfig::new(false, false, false)).unwrap();
+        assert_eq!(index.len(), 2);
+
+        let hits = index.query("s8", 550, 551);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].1[0].name, "read2");
+        assert_eq!(index.get("s8", hits[0].0).unwrap()[0].name, "read2");
+
+        assert_eq!(index.query("s8", 150, 550).len(), 2);
+        assert!(index.query("s8", 200, 500).is_empty());
+        assert!(index.query("s9", 0, 1000).is_empty());
+    }
+
+    #[test]
+    fn test_query_at_inside_between_and_outside() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t500\t600\tread3\t0\t+\t510\t590\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let index =
+            packbed_index(vec![file.path()], &PackConfig::new(false, false, false)).unwrap();
+
+        let (_, comp) = index.query_at("s8", 250).unwrap();
+        assert_eq!(comp.len(), 2);
+        assert_eq!(index.query_at("s8", 599).unwrap().1[0].name, "read3");
+
+        assert!(index.query_at("s8", 300).is_none());
+        assert!(index.query_at("s8", 400).is_none());
+        assert!(index.query_at("s8", 99).is_none());
+        assert!(index.query_at("s8", 600).is_none());
+        assert!(index.query_at("s9", 150).is_none());
+    }
+}
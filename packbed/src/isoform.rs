@@ -0,0 +1,169 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use hashbrown::HashMap;
+
+use crate::{Buckets, GenePred};
+
+// transcripts per distinct intron chain, single-exon transcripts share the
+// empty chain
+pub fn intron_chains(component: &[GenePred]) -> HashMap<Vec<(u64, u64)>, usize> {
+    let mut chains = HashMap::new();
+    for tx in component {
+        *chains.entry(tx.introns.clone()).or_default() += 1;
+    }
+
+    chains
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct WobbleRecord {
+    pub tx_a: String,
+    pub tx_b: String,
+    pub start_offset: i64,
+    pub end_offset: i64,
+}
+
+// exon pairs across members overlapping by at least min_overlap_frac of the
+// longer exon without sharing both boundaries; offsets are b - a in
+// genomic coordinates
+pub fn boundary_wobble(component: &[GenePred], min_overlap_frac: f64) -> Vec<WobbleRecord> {
+    let mut records = Vec::new();
+    for i in 0..component.len() {
+        for j in i + 1..component.len() {
+            for &(sa, ea) in &component[i].exons {
+                for &(sb, eb) in &component[j].exons {
+                    if (sa, ea) == (sb, eb) {
+                        continue;
+                    }
+
+                    let overlap = ea.min(eb).saturating_sub(sa.max(sb));
+                    let longest = (ea - sa).max(eb - sb);
+                    if longest == 0 || (overlap as f64 / longest as f64) < min_overlap_frac {
+                        continue;
+                    }
+
+                    records.push(WobbleRecord {
+                        tx_a: component[i].name.clone(),
+                        tx_b: component[j].name.clone(),
+                        start_offset: sb as i64 - sa as i64,
+                        end_offset: eb as i64 - ea as i64,
+                    });
+                }
+            }
+        }
+    }
+
+    records
+}
+
+pub fn wobblewriter<P: AsRef<Path> + Debug>(
+    file: P,
+    buckets: &Buckets,
+    min_overlap_frac: f64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+    writeln!(
+        file,
+        "chrom\tcomponent\ttx_a\ttx_b\tstart_offset\tend_offset"
+    )?;
+
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    for chr in chroms {
+        let Some(comps) = buckets.get(&chr) else {
+            continue;
+        };
+        for (i, comp) in comps.iter().enumerate() {
+            for record in boundary_wobble(comp, min_overlap_frac) {
+                writeln!(
+                    file,
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    chr, i, record.tx_a, record.tx_b, record.start_offset, record.end_offset
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// chrom, component index, transcripts, distinct chains and the size of the
+// most supported chain for every component
+pub fn isoformwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    buckets: &Buckets,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+    writeln!(file, "chrom\tcomponent\ttranscripts\tchains\ttop_chain")?;
+
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    for chr in chroms {
+        let Some(comps) = buckets.get(&chr) else {
+            continue;
+        };
+        for (i, comp) in comps.iter().enumerate() {
+            let chains = intron_chains(comp);
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}\t{}",
+                chr,
+                i,
+                comp.len(),
+                chains.len(),
+                chains.values().max().unwrap_or(&0)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packbed;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_intron_chains_counts() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t400\tread1\t0\t+\t100\t400\t0\t2\t50,50,\t0,250,\ns8\t120\t420\tread2\t0\t+\t120\t420\t0\t2\t30,70,\t0,230,\ns8\t100\t400\tread3\t0\t+\t100\t400\t0\t3\t50,50,50,\t0,120,250,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let comps = buckets.get("s8").unwrap();
+        assert_eq!(comps.len(), 1);
+
+        let chains = intron_chains(&comps[0]);
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[&vec![(150, 350)]], 2);
+        assert_eq!(chains[&vec![(150, 220), (270, 350)]], 1);
+    }
+
+    #[test]
+    fn test_boundary_wobble_offsets() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t400\tread1\t0\t+\t100\t400\t0\t2\t40,50,\t0,250,\ns8\t102\t400\tread2\t0\t+\t102\t400\t0\t2\t40,50,\t0,248,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let comps = buckets.get("s8").unwrap();
+
+        let wobble = boundary_wobble(&comps[0], 0.9);
+        assert_eq!(wobble.len(), 1);
+        assert_eq!((wobble[0].start_offset, wobble[0].end_offset), (2, 2));
+        assert!(boundary_wobble(&comps[0], 0.96).is_empty());
+    }
+}
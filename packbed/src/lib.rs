@@ -1,24 +1,85 @@
-use std::borrow::Borrow;
+use std::borrow::{Borrow, Cow};
 use std::cmp::PartialOrd;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::BTreeSet;
 use std::fmt::Debug;
 use std::fs::File;
-use std::io::{BufWriter, Read, Write};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 use dashmap::DashMap;
 use flate2::read::MultiGzDecoder;
-use hashbrown::HashMap;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hashbrown::{HashMap, HashSet};
 use memmap2::Mmap;
 use num_traits::{Num, NumCast};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
 use rmp_serde::{decode, encode};
 
+pub mod bgzf;
+pub mod coverage;
+pub mod diff;
+pub mod fix;
+pub mod gene;
+pub mod gff;
+pub mod hotspot;
+pub mod index;
+pub mod isoform;
+pub mod names;
+pub mod overlap;
 pub mod record;
-pub use record::{Bed12, GenePred};
+pub mod regions;
+pub mod session;
+pub mod sorted;
+pub mod spill;
+pub mod splice;
+pub mod stats;
+pub mod stream;
+pub mod table;
+pub mod timing;
+pub use bgzf::{read_chrom, read_gzi};
+pub use coverage::{coverage_bedgraph, exon_coverage};
+pub use diff::{component_hash, diff_components, diffwriter, ComponentDiff};
+pub use fix::{fix_bed, fix_line, Fix};
+pub use gene::{genewriter, group_by_gene};
+pub use gff::gff3writer;
+pub use hotspot::{component_hotspots, hotspotwriter};
+pub use index::{packbed_index, ComponentIndex};
+pub use isoform::{boundary_wobble, intron_chains, isoformwriter, wobblewriter, WobbleRecord};
+pub use names::{
+    duplicate_name_report, duplicatewriter, find_duplicate_names, resolve_duplicate_names,
+    DuplicateName, DuplicateNamePolicy, DuplicateNames,
+};
+pub use overlap::{
+    classified_pairs, classify_overlap, classwriter, overlap_pairs, pairwriter, OverlapClass,
+    OverlapMode, OverlapPair, OverlapType,
+};
+pub use record::{
+    is_annotation, Bed12, Bed12Reader, Bed6, GenePred, GenePredParser, Gtf, InputFormat, ParseError,
+};
+pub use regions::{partition_by_regions, read_regions, Regions};
+pub use session::{PackSession, Packer};
+pub use sorted::{par_sorted_bedwriter, sorted_bedwriter};
+pub use spill::{packbed_spill, spillwriter, SpilledComponents};
+pub use splice::{
+    annotate_splice_sites, intronwriter, novel_introns, novelintronwriter, read_fasta, SpliceClass,
+};
+pub use stats::{
+    component_stats, exon_length_stats, exonstatswriter, statswriter, ComponentStat, ExonStats,
+    LengthSummary,
+};
+pub use stream::packbed_streaming;
+pub use table::{component_expression, component_table, strand_purity};
+pub use timing::{packbed_timed, timingwriter, ChromTiming, PackTiming};
 
 pub type GenePredMap = HashMap<String, Vec<GenePred>>;
+pub type ComponentMap = HashMap<String, Vec<Vec<GenePred>>>;
+pub type Buckets = DashMap<String, Vec<Vec<GenePred>>>;
 
 pub const RGB: [&str; 10] = [
     "255,0,0",    // red
@@ -33,23 +94,63 @@ pub const RGB: [&str; 10] = [
     "172,126,0",  // brown
 ];
 
-fn reader<P: AsRef<Path> + Debug>(file: P) -> Result<String, Box<dyn std::error::Error>> {
-    match file.as_ref().extension() {
+pub(crate) fn reader<P: AsRef<Path> + Debug>(
+    file: P,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if is_stdin(&file) {
+        return read_from(std::io::stdin().lock());
+    }
+
+    let contents = match file.as_ref().extension() {
         Some(ext) => match ext.to_str() {
-            Some("gz") => with_gz(&File::open(file)?),
+            Some("gz") => with_gz(&File::open(&file)?)?,
             _ => {
-                let mut file = File::open(file)?;
+                let mut file = File::open(&file)?;
                 let mut contents = String::new();
                 file.read_to_string(&mut contents)?;
-                Ok(contents)
+                contents
             }
         },
-        None => Err("No extension found".into()),
+        None => return Err("No extension found".into()),
+    };
+
+    // GTF/GFF3 input becomes BED12 here, so everything downstream only
+    // ever sees BED12 lines
+    if record::is_annotation(&file) {
+        return Ok(Gtf::to_bed12(&contents)?);
+    }
+
+    Ok(contents)
+}
+
+// "-" reads the input from stdin
+pub(crate) fn is_stdin<P: AsRef<Path>>(file: P) -> bool {
+    file.as_ref() == Path::new("-")
+}
+
+// piped input has no extension to go by, gzip is sniffed from the magic bytes
+pub(crate) fn read_from<R: Read>(mut input: R) -> Result<String, Box<dyn std::error::Error>> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut contents = String::new();
+        MultiGzDecoder::new(&bytes[..]).read_to_string(&mut contents)?;
+        return Ok(contents);
     }
+
+    Ok(String::from_utf8(bytes)?)
 }
 
 fn with_gz(file: &File) -> Result<String, Box<dyn std::error::Error>> {
     let mmap = unsafe { Mmap::map(file)? };
+
+    // mislabeled plain text would otherwise fail deep in the decoder
+    if !mmap.starts_with(&[0x1f, 0x8b]) {
+        log::warn!(".gz input is not gzip compressed, reading it as plain text");
+        return Ok(std::str::from_utf8(&mmap)?.to_string());
+    }
+
     let mut decoder = MultiGzDecoder::new(&mmap[..]);
 
     let mut contents = String::new();
@@ -58,62 +159,444 @@ fn with_gz(file: &File) -> Result<String, Box<dyn std::error::Error>> {
     Ok(contents)
 }
 
-fn par_reader<P: AsRef<Path> + Debug + Sync + Send>(
+pub(crate) fn par_reader<P: AsRef<Path> + Debug + Sync + Send>(
     files: Vec<P>,
 ) -> Result<String, anyhow::Error> {
+    // a missing trailing newline would glue the last line to the next file
     let contents: Vec<String> = files
         .par_iter()
-        .map(|path| reader(path).unwrap_or_else(|e| panic!("Error reading file: {:?}", e)))
-        .collect();
+        .map(|path| reader(path).map_err(|e| anyhow::anyhow!("{:?}: {}", path, e)))
+        .map(|x| {
+            x.map(|mut x| {
+                if !x.is_empty() && !x.ends_with('\n') {
+                    x.push('\n');
+                }
+                x
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok(contents.concat())
 }
 
-fn unpack<P: AsRef<Path> + Debug + Sync + Send>(
+// per-chromosome record counts without parsing or storing full records
+pub fn chrom_summary<P: AsRef<Path> + Debug + Sync + Send>(
     files: Vec<P>,
-    cds_overlap: bool,
-) -> Result<GenePredMap, anyhow::Error> {
+) -> Result<Vec<(String, usize)>, anyhow::Error> {
     let contents = par_reader(files)?;
-    let tracks = parse_tracks(&contents, cds_overlap)?;
+
+    let counts = contents
+        .par_lines()
+        .filter(|x| !x.is_empty() && !x.starts_with("#"))
+        .filter_map(|x| x.split('\t').next())
+        .fold(HashMap::new, |mut acc: HashMap<&str, usize>, chr| {
+            *acc.entry(chr).or_default() += 1;
+            acc
+        })
+        .reduce(HashMap::new, |mut acc, map| {
+            for (k, v) in map {
+                *acc.entry(k).or_default() += v;
+            }
+            acc
+        });
+
+    let mut summary = counts
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect::<Vec<_>>();
+    summary.sort_unstable();
+
+    Ok(summary)
+}
+
+pub(crate) fn unpack<P: AsRef<Path> + Debug + Sync + Send>(
+    files: Vec<P>,
+    config: &PackConfig,
+) -> Result<GenePredMap, anyhow::Error> {
+    unpack_str(&read_inputs(files, config)?, config)
+}
+
+pub(crate) fn read_inputs<P: AsRef<Path> + Debug + Sync + Send>(
+    files: Vec<P>,
+    config: &PackConfig,
+) -> Result<String, anyhow::Error> {
+    match &config.chrom {
+        Some(chrom) => Ok(files
+            .par_iter()
+            .map(|x| read_chrom(x, chrom).map_err(|e| anyhow::anyhow!("{:?}: {}", x, e)))
+            .collect::<Result<Vec<_>, _>>()?
+            .concat()),
+        None => par_reader(files),
+    }
+}
+
+pub(crate) fn unpack_str(
+    contents: &str,
+    config: &PackConfig,
+) -> Result<GenePredMap, anyhow::Error> {
+    let mut tracks = parse_tracks(contents, config)?;
+    postprocess_tracks(&mut tracks, config);
 
     Ok(tracks)
 }
 
-fn parse_tracks<'a>(contents: &'a str, cds_overlap: bool) -> Result<GenePredMap, anyhow::Error> {
-    let mut tracks = contents
-        .par_lines()
-        .filter(|x| !x.starts_with("#"))
-        .filter_map(|x| Bed12::parse(x, cds_overlap).ok())
+// deduplication, score scaling and duplicate name resolution over the
+// parsed records
+pub(crate) fn postprocess_tracks(tracks: &mut GenePredMap, config: &PackConfig) {
+    if config.dedup {
+        let removed = dedup_tracks(tracks);
+        log::info!("removed {} duplicated records", removed);
+    }
+
+    if config.scale_score {
+        scale_scores(tracks);
+    }
+
+    if let Some(dups) = &config.duplicate_names {
+        let duplicates = find_duplicate_names(tracks, dups.max_distance);
+        for dup in &duplicates {
+            log::warn!(
+                "{} maps to {} loci, {}",
+                dup.name,
+                dup.loci.len(),
+                dups.policy
+            );
+        }
+        resolve_duplicate_names(tracks, &duplicates, dups.policy);
+    }
+}
+
+// drops records repeating the strand, span and exons of another on the same
+// chromosome; tracks come sorted, so the lowest name is kept. Returns how
+// many were dropped
+pub(crate) fn dedup_tracks(tracks: &mut GenePredMap) -> usize {
+    tracks
+        .par_iter_mut()
+        .map(|(_, records)| {
+            let n = records.len();
+            let mut seen = HashSet::with_capacity(n);
+            records.retain(|x| seen.insert((x.strand, x.start, x.end, x.exons.clone())));
+            n - records.len()
+        })
+        .sum()
+}
+
+// linear min/max rescale of column 5 into 0-1000 across every record,
+// unparseable scores are left as they are
+pub(crate) fn scale_scores(tracks: &mut GenePredMap) {
+    let (min, max) = tracks
+        .par_iter()
+        .flat_map_iter(|(_, x)| x.iter().filter_map(|tx| tx.score()))
         .fold(
-            || HashMap::new(),
-            |mut acc: GenePredMap, record| {
-                acc.entry(record.chrom.clone()).or_default().push(record);
-                acc
-            },
+            || (f64::INFINITY, f64::NEG_INFINITY),
+            |acc, x| (acc.0.min(x), acc.1.max(x)),
         )
         .reduce(
-            || HashMap::new(),
-            |mut acc, map| {
-                for (k, v) in map {
-                    let acc_v = acc.entry(k).or_insert(Vec::new());
-                    acc_v.extend(v);
-                }
-                acc
-            },
+            || (f64::INFINITY, f64::NEG_INFINITY),
+            |a, b| (a.0.min(b.0), a.1.max(b.1)),
         );
 
-    // sort by start/end in descending order
-    tracks.par_iter_mut().for_each(|(_, v)| {
-        v.par_sort_unstable_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+    if min > max {
+        return;
+    }
+
+    tracks.par_iter_mut().for_each(|(_, transcripts)| {
+        *transcripts = std::mem::take(transcripts)
+            .into_iter()
+            .map(|tx| match tx.score() {
+                Some(score) if max > min => {
+                    let scaled = (score - min) / (max - min) * 1000.0;
+                    tx.scoreline(scaled.round() as u64)
+                }
+                Some(_) => tx.scoreline(0),
+                None => tx,
+            })
+            .collect();
     });
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ChromNormalize {
+    Strip(String),
+    Add(String),
+}
+
+impl ChromNormalize {
+    // chr1 and 1 unify: Strip("chr") maps chr1 -> 1, Add("chr") maps 1 -> chr1
+    pub fn apply(&self, chrom: &str) -> Option<String> {
+        match self {
+            ChromNormalize::Strip(prefix) => chrom.strip_prefix(prefix.as_str()).map(String::from),
+            ChromNormalize::Add(prefix) if !chrom.starts_with(prefix.as_str()) => {
+                Some(format!("{}{}", prefix, chrom))
+            }
+            ChromNormalize::Add(_) => None,
+        }
+    }
+}
+
+// genePred and BED6 input is rewritten into the BED12 line of the same
+// transcript
+#[inline(always)]
+pub(crate) fn as_bed12<'a>(line: &'a str, config: &PackConfig) -> Result<Cow<'a, str>, ParseError> {
+    match config.input_format {
+        InputFormat::Bed12 => Ok(Cow::Borrowed(line)),
+        InputFormat::GenePred => GenePredParser::to_bed12(line).map(Cow::Owned),
+        InputFormat::Bed6 => Bed6::to_bed12(line).map(Cow::Owned),
+    }
+}
+
+// single line parse honoring the parse-time options of the config
+#[inline(always)]
+pub(crate) fn try_parse_record(line: &str, config: &PackConfig) -> Result<GenePred, ParseError> {
+    let line = as_bed12(line, config)?;
+    let shifted;
+    let line = if config.one_based_input {
+        shifted = record::shift_starts(&line, -1).ok_or(ParseError::Invalid(
+            "Cannot shift 1-based starts to 0-based",
+        ))?;
+        shifted.as_str()
+    } else {
+        &line
+    };
+
+    let record = Bed12::parse_with(line, config.overlap_mode == OverlapMode::Cds, config.strict)?;
+
+    match config
+        .chrom_normalize
+        .as_ref()
+        .and_then(|x| x.apply(&record.chrom))
+    {
+        Some(chrom) => Ok(record.rename_chrom(chrom)),
+        None => Ok(record),
+    }
+}
+
+#[inline(always)]
+pub(crate) fn parse_record(line: &str, config: &PackConfig) -> Option<GenePred> {
+    try_parse_record(line, config).ok()
+}
+
+// every line the parser would drop, with its 1-based line number in
+// contents (the concatenated inputs); comments and blank lines are skipped
+pub fn parse_errors(contents: &str, config: &PackConfig) -> Vec<(usize, ParseError)> {
+    contents
+        .lines()
+        .enumerate()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter(|(_, x)| !x.starts_with("#") && !x.is_empty())
+        .filter_map(|(i, x)| try_parse_record(x, config).err().map(|e| (i + 1, e)))
+        .collect()
+}
+
+// default for PackConfig::max_line_len; BED12 lines with thousands of
+// blocks stay well below it
+pub const MAX_LINE_LEN: usize = 1 << 20;
+
+// a single huge line usually means the file is not newline-delimited and
+// would otherwise land in one parse task
+pub(crate) fn check_line_length(contents: &str, config: &PackConfig) -> Result<(), anyhow::Error> {
+    let max = config.max_line_len.unwrap_or(MAX_LINE_LEN);
+
+    match contents.split('\n').enumerate().find(|(_, x)| x.len() > max) {
+        Some((i, line)) => anyhow::bail!(
+            "line {} is {} bytes long (limit {}), the file may be malformed or not newline-delimited",
+            i + 1,
+            line.len(),
+            max
+        ),
+        None => Ok(()),
+    }
+}
+
+// strict mode refuses the whole input at its first unparseable line
+// instead of dropping offending lines
+pub(crate) fn check_strict(contents: &str, config: &PackConfig) -> Result<(), anyhow::Error> {
+    if !config.strict {
+        return Ok(());
+    }
+
+    match parse_errors(contents, config).first() {
+        Some((line, e)) => anyhow::bail!("line {}: {}", line, e),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseStats {
+    pub records: usize,
+    pub chromosomes: usize,
+}
+
+impl ParseStats {
+    pub fn of(tracks: &GenePredMap) -> Self {
+        Self {
+            records: tracks.values().map(|x| x.len()).sum(),
+            chromosomes: tracks.len(),
+        }
+    }
+}
+
+// lines dropped by parse_tracks, by reason, with a few of them verbatim
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParseReport {
+    pub lines: usize,
+    pub skipped: usize,
+    pub reasons: HashMap<ParseError, usize>,
+    pub samples: Vec<String>,
+}
 
-    let mut count = 0;
-    count += tracks.values().map(|x| x.len()).sum::<usize>();
-    dbg!(count);
+const REPORT_SAMPLES: usize = 5;
+
+// above this fraction of skipped lines the input is likely not BED12
+pub const SKIPPED_WARN_FRACTION: f64 = 0.01;
+
+impl ParseReport {
+    fn record(&mut self, line: &str, e: ParseError) {
+        self.skipped += 1;
+        *self.reasons.entry(e).or_default() += 1;
+        if self.samples.len() < REPORT_SAMPLES {
+            self.samples.push(line.to_string());
+        }
+    }
+
+    pub(crate) fn log(&self) {
+        if self.skipped == 0 {
+            return;
+        }
+
+        let mut reasons = self
+            .reasons
+            .iter()
+            .map(|(e, n)| format!("{} ({})", e, n))
+            .collect::<Vec<_>>();
+        reasons.sort_unstable();
+
+        let msg = format!(
+            "skipped {} of {} lines: {}; e.g. {:?}",
+            self.skipped,
+            self.lines,
+            reasons.join(", "),
+            self.samples[0]
+        );
+        if self.skipped as f64 > self.lines as f64 * SKIPPED_WARN_FRACTION {
+            log::warn!("{}", msg);
+        } else {
+            log::info!("{}", msg);
+        }
+    }
+}
+
+pub(crate) fn parse_tracks(
+    contents: &str,
+    config: &PackConfig,
+) -> Result<GenePredMap, anyhow::Error> {
+    let (tracks, report) = parse_tracks_with_report(contents, config)?;
+    report.log();
 
     Ok(tracks)
 }
 
+pub fn parse_tracks_with_report(
+    contents: &str,
+    config: &PackConfig,
+) -> Result<(GenePredMap, ParseReport), anyhow::Error> {
+    check_line_length(contents, config)?;
+    check_strict(contents, config)?;
+
+    let insert = |mut acc: GenePredMap, record: GenePred| {
+        acc.entry(record.chrom.clone()).or_default().push(record);
+        acc
+    };
+    let merge = |mut acc: GenePredMap, map: GenePredMap| {
+        for (k, v) in map {
+            let acc_v = acc.entry(k).or_insert(Vec::new());
+            acc_v.extend(v);
+        }
+        acc
+    };
+
+    // failures are rare, the lock is only taken for them
+    let report = Mutex::new(ParseReport::default());
+    let input_order = config.order_mode == OrderMode::Input;
+    let parse = |(idx, line): (usize, &str)| match try_parse_record(line, config) {
+        Ok(record) if input_order => Some(GenePred {
+            order: idx,
+            ..record
+        }),
+        Ok(record) => Some(record),
+        Err(ParseError::EmptyLine) => None,
+        Err(e) => {
+            report
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .record(line, e);
+            None
+        }
+    };
+
+    let mut tracks = match config.chunk_size {
+        // fixed-size line chunks instead of rayon's own splitting
+        Some(chunk_size) => contents
+            .lines()
+            .collect::<Vec<_>>()
+            .par_chunks(chunk_size.max(1))
+            .enumerate()
+            .map(|(i, chunk)| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(j, x)| (i * chunk_size.max(1) + j, *x))
+                    .filter(|(_, x)| !x.starts_with("#"))
+                    .filter_map(parse)
+                    .fold(HashMap::new(), insert)
+            })
+            .reduce(HashMap::new, merge),
+        // line numbers need the lines indexed up front
+        None if input_order => contents
+            .lines()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .enumerate()
+            .filter(|(_, x)| !x.starts_with("#"))
+            .filter_map(parse)
+            .fold(HashMap::new, insert)
+            .reduce(HashMap::new, merge),
+        None => contents
+            .par_lines()
+            .filter(|x| !x.starts_with("#"))
+            .filter_map(|x| parse((0, x)))
+            .fold(HashMap::new, insert)
+            .reduce(HashMap::new, merge),
+    };
+
+    sort_tracks(&mut tracks);
+
+    let stats = ParseStats::of(&tracks);
+    log::info!(
+        "parsed {} records on {} chromosomes",
+        stats.records,
+        stats.chromosomes
+    );
+
+    let mut report = report.into_inner().unwrap_or_else(|e| e.into_inner());
+    report.lines = stats.records + report.skipped;
+
+    Ok((tracks, report))
+}
+
+// sort by start/end in descending order, names break ties
+pub(crate) fn sort_tracks(tracks: &mut GenePredMap) {
+    tracks.par_iter_mut().for_each(|(_, v)| {
+        v.par_sort_unstable_by(|a, b| {
+            a.start
+                .cmp(&b.start)
+                .then(b.end.cmp(&a.end))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+    });
+}
+
 #[allow(dead_code)]
 #[inline(always)]
 fn exonic_overlap<N, I>(exons_a: &BTreeSet<(N, N)>, exons_b: I) -> bool
@@ -128,100 +611,526 @@ where
     let mut exon_a = iter_a.next();
     let mut exon_b = iter_b.next();
 
-    loop {
-        match (exon_a, exon_b.as_ref()) {
-            (Some(&(start_a, end_a)), Some(exon_b_ref)) => {
-                let (start_b, end_b) = exon_b_ref.borrow();
+    while let (Some(&(start_a, end_a)), Some(exon_b_ref)) = (exon_a, exon_b.as_ref()) {
+        let (start_b, end_b) = exon_b_ref.borrow();
 
-                if start_a < *end_b && *start_b < end_a {
-                    return true;
-                }
+        if start_a < *end_b && *start_b < end_a {
+            return true;
+        }
 
-                if end_a < *end_b {
-                    exon_a = iter_a.next();
-                } else {
-                    exon_b = iter_b.next();
-                }
-            }
-            _ => break,
+        if end_a < *end_b {
+            exon_a = iter_a.next();
+        } else {
+            exon_b = iter_b.next();
         }
     }
 
     false
 }
 
-fn buckerize(
+// if base mode, tx boundaries will behave as exons ranges
+#[inline(always)]
+pub(crate) fn intervals(transcript: &GenePred, mode: OverlapMode) -> Vec<(u64, u64)> {
+    match mode {
+        OverlapMode::Boundary => vec![(transcript.start, transcript.end)],
+        OverlapMode::Exon | OverlapMode::Cds => transcript.exons.clone(),
+    }
+}
+
+pub type OverlapFn = dyn Fn(&GenePred, &GenePred) -> bool + Send + Sync;
+
+/// Caller-defined union rule for [`PackConfig::overlap_fn`].
+///
+/// Instead of the sorted sweep (one pass over sorted intervals), every pair of
+/// transcripts whose genomic spans lie within `window` bp of each other is
+/// handed to the predicate. That is `O(n * k)` predicate calls, with `k` the
+/// number of transcripts inside the window, so dense loci and wide windows get
+/// expensive quickly; the overlap mode flags are ignored while it is set.
+#[derive(Clone)]
+pub struct OverlapPredicate {
+    pub window: u64,
+    pub f: Arc<OverlapFn>,
+}
+
+impl OverlapPredicate {
+    pub fn new<F>(window: u64, f: F) -> Self
+    where
+        F: Fn(&GenePred, &GenePred) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            window,
+            f: Arc::new(f),
+        }
+    }
+}
+
+impl Debug for OverlapPredicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OverlapPredicate")
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
+
+// "r,g,b;r,g,b;..." into itemRgb entries, each channel 0-255
+pub fn parse_palette(s: &str) -> Result<Vec<String>, anyhow::Error> {
+    let palette = s
+        .split(';')
+        .filter(|x| !x.is_empty())
+        .map(|color| {
+            let channels = color
+                .split(',')
+                .map(|x| x.trim().parse::<u8>())
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+                .filter(|x| x.len() == 3)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("invalid palette color {}, expected r,g,b", color)
+                })?;
+            Ok(format!("{},{},{}", channels[0], channels[1], channels[2]))
+        })
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+    if palette.is_empty() {
+        anyhow::bail!("palette has no colors");
+    }
+
+    Ok(palette)
+}
+
+// member order inside each component
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum OrderMode {
+    // by start, longest first on ties, see sort_tracks
+    #[default]
+    Coordinate,
+    // as the records appear in the inputs, see GenePred::order
+    Input,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ColorMode {
+    // a random palette color per component, reproducible with color_seed
+    #[default]
+    Random,
+    // palette colors in component order
+    Cyclic,
+    // neighbouring and overlapping components never share a color
+    NeighborAware,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PackConfig {
+    // Cds also clips exons to the CDS while parsing, see try_parse_record
+    pub overlap_mode: OverlapMode,
+    pub colorize: bool,
+    // singletons keep their original color when colorizing
+    pub colorize_multi_only: bool,
+    // rescale column 5 into 0-1000 for track display
+    pub scale_score: bool,
+    // error out on malformed records instead of warning
+    pub strict: bool,
+    // input starts are 1-based (non-standard BED), see bedwriter_with
+    pub one_based_input: bool,
+    // only pack this chromosome, see bgzf::read_chrom
+    pub chrom: Option<String>,
+    // drops components whose members are all non-coding (cds_start == cds_end)
+    pub coding_components_only: bool,
+    // drops components with fewer transcripts, 0 and 1 both keep everything
+    pub min_component_size: usize,
+    // keeps one of each set of structurally identical records, see dedup_tracks
+    pub dedup: bool,
+    // lines per parse task, None leaves the split to rayon
+    pub chunk_size: Option<usize>,
+    // bytes per line before the input is rejected, None uses MAX_LINE_LEN
+    pub max_line_len: Option<usize>,
+    // warn when a chromosome yields more components than this, usually a
+    // delimiter or parsing problem rather than biology
+    pub warn_component_count: Option<usize>,
+    // only transcripts on the same strand are unioned by overlap_fn; the
+    // built-in overlap types always keep strands apart
+    pub same_strand_only: bool,
+    // bases two intervals must share to merge, 0 and 1 both mean any overlap
+    pub min_overlap: u64,
+    pub overlap_type: OverlapType,
+    pub color_mode: ColorMode,
+    pub color_seed: Option<u64>,
+    // itemRgb entries replacing RGB, see parse_palette
+    pub palette: Option<Vec<String>>,
+    // names shared by distant loci, detected and resolved after parsing
+    pub duplicate_names: Option<DuplicateNames>,
+    pub max_intron_len: Option<u64>,
+    pub overlap_fn: Option<OverlapPredicate>,
+    pub chrom_normalize: Option<ChromNormalize>,
+    pub input_format: InputFormat,
+    pub order_mode: OrderMode,
+}
+
+impl PackConfig {
+    // the overlap_cds/overlap_exon flag pair, see OverlapMode::from_flags
+    pub fn new(overlap_cds: bool, overlap_exon: bool, colorize: bool) -> Self {
+        Self::with_mode(OverlapMode::from_flags(overlap_cds, overlap_exon), colorize)
+    }
+
+    pub fn with_mode(overlap_mode: OverlapMode, colorize: bool) -> Self {
+        Self {
+            overlap_mode,
+            colorize,
+            ..Default::default()
+        }
+    }
+}
+
+pub(crate) fn check_component_count(chr: &str, count: usize, config: &PackConfig) {
+    if let Some(max) = config.warn_component_count.filter(|&max| count > max) {
+        log::warn!(
+            "{}: {} components exceed --warn_component_count {}, check the input for parsing problems",
+            chr,
+            count,
+            max
+        );
+    }
+}
+
+pub(crate) fn buckerize(
     tracks: GenePredMap,
-    overlap_cds: bool,
-    overlap_exon: bool,
-    colorize: bool,
+    config: &PackConfig,
 ) -> DashMap<String, Vec<Vec<GenePred>>> {
     let cmap = DashMap::new();
 
     tracks.into_par_iter().for_each(|(chr, transcripts)| {
-        let mut exons = Vec::new();
-        let mut id_map = HashMap::new();
-        let mut uf = UnionFind::new(transcripts.len());
+        let records = transcripts.len();
+        let comps = components(transcripts, config);
+        log::debug!("{}: {} records in {} components", chr, records, comps.len());
+        check_component_count(&chr, comps.len(), config);
+
+        // every component was filtered out
+        if !comps.is_empty() {
+            cmap.insert(chr, comps);
+        }
+    });
+
+    cmap
+}
+
+// (min start, max end) over the members, (u64::MAX, 0) when empty
+pub fn component_span(component: &[GenePred]) -> (u64, u64) {
+    component.iter().fold((u64::MAX, 0), |acc, x| {
+        (acc.0.min(x.start), acc.1.max(x.end))
+    })
+}
+
+// groups the transcripts of a single chromosome into overlapping components
+pub(crate) fn components(transcripts: Vec<GenePred>, config: &PackConfig) -> Vec<Vec<GenePred>> {
+    // run-through transcripts would bridge otherwise separate loci
+    let transcripts = match config.max_intron_len {
+        Some(max) => transcripts
+            .into_iter()
+            .flat_map(|x| x.split_at_introns(max))
+            .collect(),
+        None => transcripts,
+    };
+
+    let mut uf = UnionFind::new(transcripts.len());
+    match &config.overlap_fn {
+        Some(predicate) => pairwise(&transcripts, predicate, config.same_strand_only, &mut uf),
+        None => match config.overlap_type {
+            OverlapType::Any => sweep(&transcripts, config, &mut uf),
+            OverlapType::Reciprocal(f) => reciprocal(&transcripts, config, f, &mut uf),
+            OverlapType::Intron => shared_introns(&transcripts, &mut uf),
+        },
+    }
+
+    // components are indexed by their first member in sorted order, so
+    // (chrom, index) references are stable across runs and .bin round trips
+    let mut index = HashMap::new();
+    let mut groups: Vec<Vec<GenePred>> = Vec::new();
+    for (i, transcript) in transcripts.into_iter().enumerate() {
+        let root = uf.find(i);
+        let idx = *index.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[idx].push(transcript);
+    }
+
+    let groups = groups
+        .into_iter()
+        .filter(|v| !config.coding_components_only || v.iter().any(|x| x.cds_start != x.cds_end))
+        .filter(|v| v.len() >= config.min_component_size)
+        .map(|mut v| {
+            if config.order_mode == OrderMode::Input {
+                v.sort_by_key(|x| x.order);
+            }
+            v
+        })
+        .collect::<Vec<_>>();
 
-        // if base mode, tx boundaries will behave as exons ranges
-        for (i, transcript) in transcripts.iter().enumerate() {
-            id_map.insert(i, transcript);
+    if !config.colorize {
+        return groups;
+    }
 
-            if !overlap_exon && !overlap_cds {
-                exons.push((transcript.start, transcript.end, i));
+    let colors = component_colors(&groups, config);
+    groups
+        .into_iter()
+        .zip(colors)
+        .map(|(v, color)| {
+            if !config.colorize_multi_only || v.len() > 1 {
+                v.into_iter().map(|gp| gp.colorline(color)).collect()
             } else {
-                for &(start, end) in &transcript.exons {
-                    exons.push((start, end, i));
+                v
+            }
+        })
+        .collect()
+}
+
+// one palette color per component of a chromosome, in component order
+fn component_colors<'a>(groups: &[Vec<GenePred>], config: &'a PackConfig) -> Vec<&'a str> {
+    let palette = match &config.palette {
+        Some(palette) => palette.iter().map(String::as_str).collect::<Vec<_>>(),
+        None => RGB.to_vec(),
+    };
+    let n = palette.len();
+
+    let colors = match config.color_mode {
+        ColorMode::Random => match config.color_seed {
+            // seeded per chromosome so parallel chromosomes stay reproducible
+            Some(seed) => {
+                let mut hasher = DefaultHasher::new();
+                groups
+                    .first()
+                    .and_then(|x| x.first())
+                    .map(|x| x.chrom.as_str())
+                    .hash(&mut hasher);
+                let mut rng = StdRng::seed_from_u64(seed ^ hasher.finish());
+                groups.iter().map(|_| rng.gen_range(0..n)).collect()
+            }
+            None => groups.iter().map(|_| choose_color(n)).collect(),
+        },
+        ColorMode::Cyclic => (0..groups.len()).map(|i| i % n).collect(),
+        ColorMode::NeighborAware => neighbor_colors(groups, n),
+    };
+
+    colors.into_iter().map(|c: usize| palette[c]).collect()
+}
+
+// greedy coloring in genomic order: each component avoids the color of the
+// previous component and of every component whose span still overlaps it
+fn neighbor_colors(groups: &[Vec<GenePred>], n: usize) -> Vec<usize> {
+    let mut order = groups
+        .iter()
+        .enumerate()
+        .map(|(i, comp)| {
+            let span = component_span(comp);
+            (span, i)
+        })
+        .collect::<Vec<_>>();
+    order.sort_unstable();
+
+    let mut colors = vec![0; groups.len()];
+    let mut active: Vec<(u64, usize)> = Vec::new();
+    let mut prev: Option<usize> = None;
+
+    for &((start, end), i) in &order {
+        active.retain(|&(active_end, _)| active_end > start);
+
+        let taken = active
+            .iter()
+            .map(|&(_, c)| c)
+            .chain(prev)
+            .collect::<Vec<_>>();
+        let first = prev.map_or(0, |x| x + 1);
+        let color = (0..n)
+            .map(|k| (first + k) % n)
+            .find(|c| !taken.contains(c))
+            .unwrap_or(first % n);
+
+        colors[i] = color;
+        active.push((end, color));
+        prev = Some(color);
+    }
+
+    colors
+}
+
+// merges neighbouring components on a chromosome whose genomic spans are
+// at most max_gap apart; colors are kept as assigned by buckerize
+pub fn merge_nearby_components(
+    buckets: DashMap<String, Vec<Vec<GenePred>>>,
+    max_gap: u64,
+    stranded: bool,
+) -> DashMap<String, Vec<Vec<GenePred>>> {
+    buckets
+        .into_par_iter()
+        .map(|(chr, comps)| {
+            let mut spans = comps
+                .into_iter()
+                .map(|comp| {
+                    let (start, end) = component_span(&comp);
+                    let strand = if comp.iter().all(|x| x.strand == comp[0].strand) {
+                        comp[0].strand
+                    } else {
+                        '.'
+                    };
+                    (start, end, strand, comp)
+                })
+                .collect::<Vec<_>>();
+            spans.sort_by_key(|x| (x.0, x.1));
+
+            let mut merged: Vec<(u64, u64, char, Vec<GenePred>)> = Vec::new();
+            for (start, end, strand, comp) in spans {
+                if stranded {
+                    // only the last component of the same strand is a candidate
+                    if let Some(prev) = merged.iter_mut().rev().find(|x| x.2 == strand) {
+                        if strand != '.' && start <= prev.1.saturating_add(max_gap) {
+                            prev.1 = prev.1.max(end);
+                            prev.3.extend(comp);
+                            continue;
+                        }
+                    }
+                } else if let Some(prev) = merged.last_mut() {
+                    if start <= prev.1.saturating_add(max_gap) {
+                        prev.1 = prev.1.max(end);
+                        prev.3.extend(comp);
+                        continue;
+                    }
                 }
+
+                merged.push((start, end, strand, comp));
             }
+
+            (chr, merged.into_iter().map(|x| x.3).collect())
+        })
+        .collect()
+}
+
+// strands are swept apart, overlapping transcripts on opposite strands
+// never share a component in this mode
+fn sweep(transcripts: &[GenePred], config: &PackConfig, uf: &mut UnionFind) {
+    let (mut plus, mut minus) = (Vec::new(), Vec::new());
+
+    for (i, transcript) in transcripts.iter().enumerate() {
+        let exons = if transcript.strand == '-' {
+            &mut minus
+        } else {
+            &mut plus
+        };
+        for (start, end) in intervals(transcript, config.overlap_mode) {
+            exons.push((start, end, i));
         }
+    }
 
-        exons.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    sweep_intervals(plus, config.min_overlap, uf);
+    sweep_intervals(minus, config.min_overlap, uf);
+}
 
-        let mut prev_end = exons[0].1;
-        let mut prev_idx = exons[0].2;
-        for &(start, end, idx) in &exons[1..] {
-            if start < prev_end {
+fn sweep_intervals(mut exons: Vec<(u64, u64, usize)>, min_overlap: u64, uf: &mut UnionFind) {
+    // records without blocks (blockCount 0, empty block fields) never
+    // union and come out as singletons
+    if exons.is_empty() {
+        return;
+    }
+    exons.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    let min_overlap = min_overlap.max(1);
+    let mut prev_end = exons[0].1;
+    let mut prev_idx = exons[0].2;
+    for &(start, end, idx) in &exons[1..] {
+        if start < prev_end {
+            if prev_end.min(end) - start >= min_overlap {
                 uf.union(prev_idx, idx);
-                prev_end = prev_end.max(end);
-            } else {
-                // no overlap, update prev_end and prev_idx
-                prev_end = end;
+            } else if end > prev_end {
+                // too short to merge, the interval reaching further leads
                 prev_idx = idx;
             }
+            prev_end = prev_end.max(end);
+        } else {
+            // no overlap, update prev_end and prev_idx
+            prev_end = end;
+            prev_idx = idx;
         }
+    }
+}
 
-        let mut groups = HashMap::new();
-        for i in 0..transcripts.len() {
-            let root = uf.find(i);
-            groups
-                .entry(root)
-                .or_insert_with(Vec::new)
-                .push(id_map[&i].clone());
+// unions pairs whose shared length covers f of both transcripts; the
+// relation is not transitive but components are its closure, so a chain
+// A-B, B-C ends up in one component even when A-C fails the fraction
+fn reciprocal(transcripts: &[GenePred], config: &PackConfig, f: f64, uf: &mut UnionFind) {
+    let (shared, lengths) = overlap::shared_bases(transcripts, config.overlap_mode);
+
+    for ((i, j), overlap) in shared {
+        let overlap = overlap as f64;
+        if overlap >= f * lengths[i] as f64 && overlap >= f * lengths[j] as f64 {
+            uf.union(i, j);
         }
+    }
+}
 
-        let comps = groups
-            .into_iter()
-            .map(|(_, v)| {
-                if colorize {
-                    let color = choose_color();
-                    v.into_iter().map(|gp| gp.colorline(color)).collect()
-                } else {
-                    v
+// unions every transcript with the first one seen carrying each intron on
+// the same strand
+fn shared_introns(transcripts: &[GenePred], uf: &mut UnionFind) {
+    let mut seen: HashMap<(char, (u64, u64)), usize> = HashMap::new();
+
+    for (i, transcript) in transcripts.iter().enumerate() {
+        for &intron in &transcript.introns {
+            match seen.get(&(transcript.strand, intron)) {
+                Some(&j) => uf.union(j, i),
+                None => {
+                    seen.insert((transcript.strand, intron), i);
                 }
-            })
-            .collect();
+            }
+        }
+    }
+}
 
-        cmap.insert(chr, comps);
-    });
+// windowed pairwise scan over genomic spans for caller-defined predicates
+fn pairwise(
+    transcripts: &[GenePred],
+    predicate: &OverlapPredicate,
+    same_strand_only: bool,
+    uf: &mut UnionFind,
+) {
+    let mut spans = transcripts
+        .iter()
+        .enumerate()
+        .map(|(i, x)| ((x.start, x.end), i))
+        .collect::<Vec<_>>();
+    spans.sort_unstable();
+
+    for (k, &((_, end), i)) in spans.iter().enumerate() {
+        for &((start, _), j) in &spans[k + 1..] {
+            if start > end.saturating_add(predicate.window) {
+                break;
+            }
 
-    cmap
+            if same_strand_only && transcripts[i].strand != transcripts[j].strand {
+                continue;
+            }
+
+            if (predicate.f)(&transcripts[i], &transcripts[j]) {
+                uf.union(i, j);
+            }
+        }
+    }
 }
 
-fn choose_color<'a>() -> &'a str {
+fn choose_color(n: usize) -> usize {
     let mut rng = rand::thread_rng();
-    let idx = rng.gen_range(0..RGB.len());
-    RGB[idx]
+    rng.gen_range(0..n)
+}
+
+/// Parses a BED12 file (or `.gz`, GTF/GFF3, `-` for stdin) into records per
+/// chromosome without packing them into components.
+///
+/// Records of each chromosome come sorted by start, longest first on ties.
+/// Unparseable lines are skipped and logged, as in [`packbed`]; `cds_overlap`
+/// clips exons to the CDS like `--overlap_mode cds`.
+pub fn parse_bed12_file<P: AsRef<Path> + Debug>(
+    path: P,
+    cds_overlap: bool,
+) -> Result<GenePredMap, anyhow::Error> {
+    let contents = reader(&path).map_err(|e| anyhow::anyhow!("{:?}: {}", path, e))?;
+    parse_tracks(&contents, &PackConfig::new(cds_overlap, false, false))
 }
 
 pub fn packbed<T: AsRef<Path> + Debug + Send + Sync>(
@@ -230,40 +1139,283 @@ pub fn packbed<T: AsRef<Path> + Debug + Send + Sync>(
     overlap_exon: bool,
     colorize: bool,
 ) -> Result<DashMap<String, Vec<Vec<GenePred>>>, anyhow::Error> {
-    let tracks = unpack(bed, overlap_cds).unwrap();
-    let buckets = buckerize(tracks, overlap_cds, overlap_exon, colorize);
+    packbed_with(bed, &PackConfig::new(overlap_cds, overlap_exon, colorize))
+}
+
+pub fn packbed_with<T: AsRef<Path> + Debug + Send + Sync>(
+    bed: Vec<T>,
+    config: &PackConfig,
+) -> Result<DashMap<String, Vec<Vec<GenePred>>>, anyhow::Error> {
+    packbed_from_str_with(&read_inputs(bed, config)?, config)
+}
+
+// packs BED content already in memory, no file is touched
+pub fn packbed_from_str(
+    contents: &str,
+    overlap_cds: bool,
+    overlap_exon: bool,
+    colorize: bool,
+) -> Result<DashMap<String, Vec<Vec<GenePred>>>, anyhow::Error> {
+    packbed_from_str_with(
+        contents,
+        &PackConfig::new(overlap_cds, overlap_exon, colorize),
+    )
+}
+
+pub fn packbed_from_str_with(
+    contents: &str,
+    config: &PackConfig,
+) -> Result<DashMap<String, Vec<Vec<GenePred>>>, anyhow::Error> {
+    let tracks = unpack_str(contents, config)?;
+    let buckets = buckerize(tracks, config);
 
     Ok(buckets)
 }
 
+// packbed_with that also hands back what was parsed, before any packing
+pub fn packbed_with_stats<T: AsRef<Path> + Debug + Send + Sync>(
+    bed: Vec<T>,
+    config: &PackConfig,
+) -> Result<(Buckets, ParseStats), anyhow::Error> {
+    let tracks = unpack(bed, config)?;
+    let stats = ParseStats::of(&tracks);
+
+    Ok((buckerize(tracks, config), stats))
+}
+
+// packbed_with that aborts at the first unparseable line, see check_strict
+pub fn packbed_strict<T: AsRef<Path> + Debug + Send + Sync>(
+    bed: Vec<T>,
+    config: &PackConfig,
+) -> Result<DashMap<String, Vec<Vec<GenePred>>>, anyhow::Error> {
+    let config = PackConfig {
+        strict: true,
+        ..config.clone()
+    };
+    packbed_with(bed, &config)
+}
+
+// .bin files open with BIN_MAGIC, the format version as u16 LE and the
+// writing packbed version (u8 length + bytes). Version 1 follows with the
+// whole map as one msgpack value; version 2 with a msgpack index of
+// (chrom, blob length) and then one msgpack blob per chromosome, in index
+// order, so chromosomes decode independently
+const BIN_MAGIC: &[u8; 4] = b"PKBD";
+pub const BIN_FORMAT_VERSION: u16 = 2;
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+// a .zst path zstd-compresses the whole file, header included
 pub fn binwriter<P: AsRef<Path> + Debug>(
     file: P,
     contents: DashMap<String, Vec<Vec<GenePred>>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = File::create(file)?;
-    let contents = contents.into_iter().collect::<HashMap<_, _>>();
+    let compress = file.as_ref().extension().is_some_and(|x| x == "zst");
+    let file = BufWriter::new(File::create(file)?);
+
+    if compress {
+        let mut encoder = zstd::Encoder::new(file, 0)?;
+        binwriter_to(&mut encoder, contents)?;
+        encoder.finish()?.flush()?;
+    } else {
+        let mut file = file;
+        binwriter_to(&mut file, contents)?;
+        file.flush()?;
+    }
 
-    encode::write(&mut file, &contents)?;
     Ok(())
 }
 
-pub fn bedwriter<P: AsRef<Path> + Debug>(
-    file: P,
+pub fn binwriter_to<W: Write>(
+    out: &mut W,
     contents: DashMap<String, Vec<Vec<GenePred>>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = BufWriter::new(File::create(file)?);
+    let mut blobs = contents
+        .into_par_iter()
+        .map(|(chr, comps)| Ok((chr, encode::to_vec(&comps)?)))
+        .collect::<Result<Vec<_>, encode::Error>>()?;
+    blobs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    let version = env!("CARGO_PKG_VERSION");
+    out.write_all(BIN_MAGIC)?;
+    out.write_all(&BIN_FORMAT_VERSION.to_le_bytes())?;
+    out.write_all(&[version.len() as u8])?;
+    out.write_all(version.as_bytes())?;
+
+    let index = blobs
+        .iter()
+        .map(|(chr, blob)| (chr, blob.len() as u64))
+        .collect::<Vec<_>>();
+    encode::write(out, &index)?;
+    for (_, blob) in &blobs {
+        out.write_all(blob)?;
+    }
 
-    for (_, components) in contents {
-        for component in components {
-            for tx in component {
-                writeln!(file, "{}", tx.line())?;
-            }
+    Ok(())
+}
+
+// chromosome -> components -> full GenePred records, for consumers
+// without a msgpack reader
+pub fn jsonwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    contents: DashMap<String, Vec<Vec<GenePred>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+    let contents = contents.into_iter().collect::<HashMap<_, _>>();
+
+    serde_json::to_writer(&mut file, &contents)?;
+    file.flush()?;
+    Ok(())
+}
+
+pub fn bedwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    contents: DashMap<String, Vec<Vec<GenePred>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    bedwriter_with(file, contents, false, false)
+}
+
+// plain or gzip BED output; finish() flushes and, for gzip, writes the
+// trailer so failures surface instead of being lost on drop
+pub(crate) enum BedOutput {
+    Plain(BufWriter<File>),
+    Gz(BufWriter<GzEncoder<File>>),
+}
+
+impl BedOutput {
+    pub(crate) fn create<P: AsRef<Path>>(path: P, compress: bool) -> std::io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(if compress {
+            BedOutput::Gz(BufWriter::new(GzEncoder::new(file, Compression::default())))
+        } else {
+            BedOutput::Plain(BufWriter::new(file))
+        })
+    }
+
+    pub(crate) fn finish(self) -> std::io::Result<()> {
+        match self {
+            BedOutput::Plain(mut out) => out.flush(),
+            BedOutput::Gz(out) => out.into_inner()?.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for BedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            BedOutput::Plain(out) => out.write(buf),
+            BedOutput::Gz(out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            BedOutput::Plain(out) => out.flush(),
+            BedOutput::Gz(out) => out.flush(),
+        }
+    }
+}
+
+// sorted writes chromosomes lexically and records by genomic start/end
+// instead of component by component
+//
+// NOTE: one_based writes chromStart/thickStart + 1, which is NOT valid BED
+// and only meant for tools expecting 1-based starts; read such files back
+// with PackConfig::one_based_input
+pub fn bedwriter_with<P: AsRef<Path> + Debug>(
+    file: P,
+    contents: DashMap<String, Vec<Vec<GenePred>>>,
+    one_based: bool,
+    sorted: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let compress = file.as_ref().extension().is_some_and(|x| x == "gz");
+    let mut file = BedOutput::create(file, compress)?;
+    bedwriter_to(&mut file, contents, one_based, sorted)?;
+    file.finish()?;
+
+    Ok(())
+}
+
+// the writing half of bedwriter_with for any sink, e.g. a locked stdout
+pub fn bedwriter_to<W: Write>(
+    out: &mut W,
+    contents: DashMap<String, Vec<Vec<GenePred>>>,
+    one_based: bool,
+    sorted: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let records: Box<dyn Iterator<Item = GenePred>> = if sorted {
+        Box::new(
+            sorted::sorted_chroms(contents)
+                .into_iter()
+                .flat_map(|(_, x)| x),
+        )
+    } else {
+        Box::new(contents.into_iter().flat_map(|(_, x)| x).flatten())
+    };
+
+    for tx in records {
+        if one_based {
+            let line = record::shift_starts(tx.line(), 1)
+                .ok_or("ERROR: Cannot shift coordinates of record")?;
+            writeln!(out, "{}", line)?;
+        } else {
+            writeln!(out, "{}", tx.line())?;
         }
     }
 
     Ok(())
 }
 
+// renames every record to {name}|comp_{chr}_{i}, line included, so merged
+// outputs can be grouped back by component
+pub fn tag_components(contents: Buckets) -> Buckets {
+    contents
+        .into_par_iter()
+        .map(|(chr, comps)| {
+            let comps = comps
+                .into_iter()
+                .enumerate()
+                .map(|(i, comp)| {
+                    comp.into_iter()
+                        .map(|tx| {
+                            let name = format!("{}|comp_{}_{}", tx.name, chr, i);
+                            tx.rename(name)
+                        })
+                        .collect()
+                })
+                .collect();
+            (chr, comps)
+        })
+        .collect()
+}
+
+// one merged model per component named comp_{chr}_{i}, see merge_component;
+// a model carries a single strand, so mixed-strand components are an error
+pub fn collapse_components(contents: Buckets) -> Result<Buckets, anyhow::Error> {
+    contents
+        .into_par_iter()
+        .map(|(chr, comps)| {
+            let comps = comps
+                .into_iter()
+                .enumerate()
+                .filter(|(_, comp)| !comp.is_empty())
+                .map(|(i, comp)| {
+                    if comp.iter().any(|x| x.strand != comp[0].strand) {
+                        anyhow::bail!(
+                            "comp_{}_{} mixes strands and cannot be collapsed, pack with --same_strand_only",
+                            chr,
+                            i
+                        );
+                    }
+                    Ok(vec![
+                        merge_component(&comp)?.rename(format!("comp_{}_{}", chr, i))
+                    ])
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((chr, comps))
+        })
+        .collect()
+}
+
 pub fn get_component<T: AsRef<Path> + Debug + Send + Sync>(
     bed: Vec<T>,
     hint: Option<Vec<(String, Vec<usize>)>>,
@@ -271,110 +1423,559 @@ pub fn get_component<T: AsRef<Path> + Debug + Send + Sync>(
     overlap_cds: Option<bool>,
     overlap_exon: Option<bool>,
     colorize: Option<bool>,
-) {
+) -> Result<(), anyhow::Error> {
     let buckets = packbed(
         bed,
         overlap_cds.unwrap_or(false),
         overlap_exon.unwrap_or(false),
         colorize.unwrap_or(false),
-    )
-    .expect("Error packing bed files");
+    )?;
 
     // [(chr, [1,2,3,4]), (chr, [5,6,7])] fmt to get components
 
     match hint {
         Some(hint) => {
-            hint.into_par_iter().for_each(|(chr, comps)| {
-                if let Some(bucket) = buckets.get(&chr) {
-                    comps.into_par_iter().for_each(|comp| {
-                        let filename = format!("{}_{}.bed", chr, comp);
-                        let mut file = BufWriter::new(File::create(&filename).unwrap());
-
-                        bucket[comp].iter().for_each(|x| {
-                            writeln!(file, "{}", x.line()).unwrap();
-                        });
-                    });
-                } else {
-                    eprintln!("Chromosome {} not found in buckets", chr);
+            // every hint is checked before anything is written
+            for (chr, comps) in &hint {
+                let bucket = buckets
+                    .get(chr)
+                    .ok_or_else(|| anyhow::anyhow!("chromosome {} not found in buckets", chr))?;
+                if let Some(comp) = comps.iter().find(|&&x| x >= bucket.len()) {
+                    anyhow::bail!(
+                        "component {} out of range, {} has {} components",
+                        comp,
+                        chr,
+                        bucket.len()
+                    );
                 }
-            });
+            }
+
+            hint.into_par_iter()
+                .try_for_each(|(chr, comps)| -> Result<(), anyhow::Error> {
+                    let bucket = buckets
+                        .get(&chr)
+                        .expect("ERROR: hint chromosome vanished after validation");
+                    comps
+                        .into_par_iter()
+                        .try_for_each(|comp| -> Result<(), anyhow::Error> {
+                            let filename = format!("{}_{}.bed", chr, comp);
+                            let mut file = BufWriter::new(File::create(&filename)?);
+
+                            for x in &bucket[comp] {
+                                writeln!(file, "{}", x.line())?;
+                            }
+                            Ok(())
+                        })
+                })?;
         }
         None => {
             let mut f_out = match out {
-                Some(x) => BufWriter::new(File::create(x).unwrap()),
-                None => BufWriter::new(File::create("comp.bed").unwrap()),
+                Some(x) => BufWriter::new(File::create(x)?),
+                None => BufWriter::new(File::create("comp.bed")?),
             };
 
-            let bs = buckets.clone().into_read_only();
-            let k = bs.keys().next().unwrap();
+            // every component, chromosomes in lexical order like bedwriter
+            let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+            chroms.sort_unstable();
 
-            buckets
-                .get(k)
-                .unwrap()
-                .first()
-                .unwrap()
-                .iter()
-                .for_each(|x| {
-                    writeln!(f_out, "{}", x.line()).unwrap();
-                });
+            if chroms.is_empty() {
+                log::warn!("no components found, writing an empty file");
+            }
+
+            for chr in chroms {
+                let bucket = buckets
+                    .get(&chr)
+                    .expect("ERROR: chromosome vanished from buckets");
+                for x in bucket.iter().flatten() {
+                    writeln!(f_out, "{}", x.line())?;
+                }
+            }
+            f_out.flush()?;
         }
     }
+
+    Ok(())
 }
 
 pub fn binreader<P: AsRef<Path> + Debug>(
     file: P,
-) -> Result<HashMap<String, Vec<Vec<GenePred>>>, Box<dyn std::error::Error>> {
-    let file = File::open(file)?;
-    let data: HashMap<String, Vec<Vec<GenePred>>> = decode::from_read(file)?;
+) -> Result<ComponentMap, Box<dyn std::error::Error>> {
+    binreader_from(BufReader::new(File::open(file)?))
+}
+
+// zstd-compressed input is detected by its magic bytes, not the extension
+pub fn binreader_from<R: Read>(mut input: R) -> Result<ComponentMap, Box<dyn std::error::Error>> {
+    let mut magic = [0; 4];
+    if input.read_exact(&mut magic).is_err() {
+        return Err("not a packbed .bin file, or one written before format versioning".into());
+    }
+
+    if magic == ZSTD_MAGIC {
+        decode_bin(zstd::Decoder::new(magic.chain(input))?)
+    } else {
+        decode_bin(magic.chain(input))
+    }
+}
+
+fn decode_bin<R: Read>(mut input: R) -> Result<ComponentMap, Box<dyn std::error::Error>> {
+    let mut magic = [0; 4];
+    if input.read_exact(&mut magic).is_err() || &magic != BIN_MAGIC {
+        return Err("not a packbed .bin file, or one written before format versioning".into());
+    }
+
+    let mut header = [0; 3];
+    input.read_exact(&mut header)?;
+    let format = u16::from_le_bytes([header[0], header[1]]);
+    let mut writer = vec![0; header[2] as usize];
+    input.read_exact(&mut writer)?;
+
+    match format {
+        1 => Ok(decode::from_read(input)?),
+        2 => {
+            let mut body = Vec::new();
+            input.read_to_end(&mut body)?;
+
+            let mut rest = &body[..];
+            let index: Vec<(String, u64)> = decode::from_read(&mut rest)?;
+
+            let mut blobs = Vec::with_capacity(index.len());
+            for (chr, len) in index {
+                let len = usize::try_from(len)
+                    .ok()
+                    .filter(|&x| x <= rest.len())
+                    .ok_or("truncated .bin file")?;
+                let (blob, tail) = rest.split_at(len);
+                blobs.push((chr, blob));
+                rest = tail;
+            }
+
+            let data = blobs
+                .into_par_iter()
+                .map(|(chr, blob)| Ok((chr, decode::from_slice(blob)?)))
+                .collect::<Result<ComponentMap, decode::Error>>()?;
+            Ok(data)
+        }
+        _ => Err(format!(
+            "unsupported .bin format version {} (written by packbed {}), expected 1 or {}",
+            format,
+            String::from_utf8_lossy(&writer),
+            BIN_FORMAT_VERSION
+        )
+        .into()),
+    }
+}
+
+// distinct exonic intervals of a component: the members' genomic exons
+// merged where they overlap, sorted
+pub fn exon_catalog(component: &[GenePred]) -> Vec<(u64, u64)> {
+    let mut exons = component
+        .iter()
+        .flat_map(|x| x.exons.iter().copied())
+        .collect::<Vec<_>>();
+    exons.sort_unstable();
+
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(exons.len());
+    for (start, end) in exons {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+}
+
+// BED6 of every catalog exon named compN.exonM, N counting components
+// over chromosomes in lexical order
+pub fn exoncatalogwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    buckets: &Buckets,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    let mut n = 0;
+    for chr in chroms {
+        let Some(comps) = buckets.get(&chr) else {
+            continue;
+        };
+        for comp in comps.iter().filter(|x| !x.is_empty()) {
+            n += 1;
+            let strand = if comp.iter().all(|x| x.strand == comp[0].strand) {
+                comp[0].strand
+            } else {
+                '.'
+            };
+
+            for (m, (start, end)) in exon_catalog(comp).into_iter().enumerate() {
+                writeln!(
+                    file,
+                    "{}\t{}\t{}\tcomp{}.exon{}\t0\t{}",
+                    chr,
+                    start,
+                    end,
+                    n,
+                    m + 1,
+                    strand
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// chrom and index of the n largest components by member count, ties broken
+// by chrom and index so the selection is stable
+pub fn top_components(buckets: &Buckets, n: usize) -> Vec<(String, usize)> {
+    let mut sizes = buckets
+        .iter()
+        .flat_map(|x| {
+            let chr = x.key().clone();
+            x.value()
+                .iter()
+                .enumerate()
+                .map(|(i, comp)| (comp.len(), chr.clone(), i))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    sizes.sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| (&a.1, a.2).cmp(&(&b.1, b.2))));
+
+    sizes
+        .into_iter()
+        .take(n)
+        .map(|(_, chr, i)| (chr, i))
+        .collect()
+}
+
+// the n largest components in one BED, each preceded by a
+// "# {chr}_{i} {size}" comment line
+pub fn topwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    buckets: &Buckets,
+    n: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    for (chr, i) in top_components(buckets, n) {
+        let Some(comps) = buckets.get(&chr) else {
+            continue;
+        };
+        writeln!(file, "# {}_{} {}", chr, i, comps[i].len())?;
+        for tx in &comps[i] {
+            writeln!(file, "{}", tx.line())?;
+        }
+    }
+
+    Ok(())
+}
+
+// single model spanning a component: exons are the flattened union of the
+// members' genomic exons and the CDS spans every coding member. Members
+// without blocks leave the catalog empty, the span then is the only block
+pub fn merge_component(component: &[GenePred]) -> Result<GenePred, anyhow::Error> {
+    if component.is_empty() {
+        anyhow::bail!("cannot merge an empty component");
+    }
+
+    let mut merged = exon_catalog(component);
+    if merged.is_empty() {
+        merged.push(component_span(component));
+    }
+
+    let (start, end) = (merged[0].0, merged[merged.len() - 1].1);
+    let (cds_start, cds_end) = component
+        .iter()
+        .filter(|x| x.cds_start != x.cds_end)
+        .map(|x| (x.cds_start, x.cds_end))
+        .fold(None, |acc: Option<(u64, u64)>, (s, e)| {
+            Some(acc.map_or((s, e), |(a, b)| (a.min(s), b.max(e))))
+        })
+        .unwrap_or((start, start));
+
+    let line = format!(
+        "{}\t{}\t{}\t{}\t0\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        component[0].chrom,
+        start,
+        end,
+        component[0].name,
+        component[0].strand,
+        cds_start,
+        cds_end,
+        component[0].rgb,
+        merged.len(),
+        merged
+            .iter()
+            .map(|(s, e)| format!("{},", e - s))
+            .collect::<String>(),
+        merged
+            .iter()
+            .map(|(s, _)| format!("{},", s - start))
+            .collect::<String>()
+    );
+
+    Bed12::parse(&line, false)
+        .map_err(|e| anyhow::anyhow!("merged component {} is not valid BED12: {}", line, e))
+}
+
+// columns appended to BED12 by detailwriter
+const DETAIL_FIELDS: [(&str, &str, &str); 3] = [
+    ("string", "componentId", "Component as chrom_index"),
+    ("uint", "csize", "Number of transcripts in the component"),
+    ("string", "source", "Source of the records"),
+];
+
+// autoSql for the BED12+3 written by detailwriter, for bedToBigBed -as=
+pub fn autosql_schema(name: &str) -> String {
+    let mut schema = format!(
+        "table {}\n\"BED12+{} of packbed components\"\n(\n",
+        name,
+        DETAIL_FIELDS.len()
+    );
+    for (kind, field, desc) in [
+        (
+            "string",
+            "chrom",
+            "Reference sequence chromosome or scaffold",
+        ),
+        ("uint", "chromStart", "Start position in chromosome"),
+        ("uint", "chromEnd", "End position in chromosome"),
+        ("string", "name", "Name of item"),
+        ("uint", "score", "Score (0-1000)"),
+        ("char[1]", "strand", "+ or -"),
+        (
+            "uint",
+            "thickStart",
+            "Start of where display should be thick (start codon)",
+        ),
+        (
+            "uint",
+            "thickEnd",
+            "End of where display should be thick (stop codon)",
+        ),
+        ("uint", "reserved", "Used as itemRgb as of 2004"),
+        ("int", "blockCount", "Number of blocks"),
+        (
+            "int[blockCount]",
+            "blockSizes",
+            "Comma separated list of block sizes",
+        ),
+        (
+            "int[blockCount]",
+            "chromStarts",
+            "Start positions relative to chromStart",
+        ),
+    ]
+    .iter()
+    .chain(DETAIL_FIELDS.iter())
+    {
+        schema.push_str(&format!("    {} {}; \"{}\"\n", kind, field, desc));
+    }
+    schema.push_str(")\n");
+
+    schema
+}
+
+// BED12 plus componentId, csize and source columns, with the matching
+// autoSql written next to it as <file>.as
+pub fn detailwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    contents: DashMap<String, Vec<Vec<GenePred>>>,
+    source: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut schema = file.as_ref().as_os_str().to_owned();
+    schema.push(".as");
+    std::fs::write(schema, autosql_schema("packbedDetail"))?;
+
+    let mut out = BufWriter::new(File::create(file)?);
+    let mut contents = contents.into_iter().collect::<Vec<_>>();
+    contents.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    for (chr, components) in contents {
+        for (i, component) in components.iter().enumerate() {
+            for tx in component {
+                let bed12 = tx
+                    .line()
+                    .split('\t')
+                    .take(12)
+                    .collect::<Vec<_>>()
+                    .join("\t");
+                writeln!(
+                    out,
+                    "{}\t{}_{}\t{}\t{}",
+                    bed12,
+                    chr,
+                    i,
+                    component.len(),
+                    source
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// transcript -> (chrom, component index) join table, chromosomes in
+// lexical order and indices as produced by buckerize
+pub fn membership_table(buckets: &Buckets) -> String {
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    let mut table = String::new();
+    for chr in chroms {
+        let Some(comps) = buckets.get(&chr) else {
+            continue;
+        };
+        for (i, comp) in comps.iter().enumerate() {
+            for tx in comp {
+                table.push_str(&format!("{}\t{}\t{}\n", tx.name, chr, i));
+            }
+        }
+    }
 
-    Ok(data)
+    table
 }
 
 pub fn compwriter<T: AsRef<Path> + Debug + Sync>(
     contents: DashMap<String, Vec<Vec<GenePred>>>,
     output: T,
     subdirs: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    compwriter_with(contents, output, subdirs, None, false, false)
+}
+
+// with max_files only the largest components get their own file, the rest
+// are bundled into output/overflow.bed in chromosome and index order
+pub fn compwriter_with<T: AsRef<Path> + Debug + Sync>(
+    contents: DashMap<String, Vec<Vec<GenePred>>>,
+    output: T,
+    subdirs: bool,
+    max_files: Option<usize>,
+    sorted: bool,
+    compress: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     std::fs::create_dir_all(&output)?;
 
-    contents.iter().par_bridge().for_each(|comps| {
+    let keep = max_files.map(|n| {
+        let mut sizes = contents
+            .iter()
+            .flat_map(|x| {
+                let chr = x.key().clone();
+                x.value()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, comp)| (comp.len(), chr.clone(), i))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        sizes
+            .par_sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| (&a.1, a.2).cmp(&(&b.1, b.2))));
+
+        sizes
+            .into_iter()
+            .take(n)
+            .map(|(_, chr, i)| (chr, i))
+            .collect::<hashbrown::HashSet<_>>()
+    });
+    let kept = |chr: &String, i: usize| keep.as_ref().is_none_or(|x| x.contains(&(chr.clone(), i)));
+
+    if keep.is_some() {
+        let mut overflow = BufWriter::new(File::create(output.as_ref().join("overflow.bed"))?);
+        let mut chroms = contents.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+        chroms.sort_unstable();
+
+        for chr in chroms {
+            if let Some(comps) = contents.get(&chr) {
+                for (_, comp) in comps.iter().enumerate().filter(|(i, _)| !kept(&chr, *i)) {
+                    for tx in comp {
+                        writeln!(overflow, "{}", tx.line())?;
+                    }
+                }
+            }
+        }
+    }
+
+    let ext = if compress { "bed.gz" } else { "bed" };
+    contents.iter().par_bridge().try_for_each(|comps| {
         let chr = comps.key();
         let buckets = comps.value();
 
         buckets
             .iter()
             .enumerate()
+            .filter(|(i, _)| kept(chr, *i))
             .par_bridge()
-            .for_each(|(i, bucket)| {
+            .try_for_each(|(i, bucket)| -> std::io::Result<()> {
                 let filename = if subdirs {
                     std::fs::create_dir_all(format!(
                         "{}/comp_{}_{}",
                         output.as_ref().display(),
                         chr,
                         i
-                    ))
-                    .expect("ERROR: Could not create directory");
+                    ))?;
 
                     format!(
-                        "{}/comp_{}_{}/{}_{}.bed",
+                        "{}/comp_{}_{}/{}_{}.{}",
                         output.as_ref().display(),
                         chr,
                         i,
                         chr,
-                        i
+                        i,
+                        ext
                     )
                 } else {
-                    format!("{}/{}_{}.bed", output.as_ref().display(), chr, i)
+                    format!("{}/{}_{}.{}", output.as_ref().display(), chr, i, ext)
                 };
 
-                let mut file =
-                    BufWriter::new(File::create(&filename).expect("ERROR: Could not create file"));
+                let mut file = BedOutput::create(&filename, compress)?;
 
-                bucket.iter().for_each(|x| {
-                    writeln!(file, "{}", x.line()).unwrap();
-                });
-            });
-    });
+                let mut records = bucket.iter().collect::<Vec<_>>();
+                if sorted {
+                    records.sort_by_key(|x| (x.start, x.end));
+                }
+
+                for x in records {
+                    writeln!(file, "{}", x.line())?;
+                }
+                file.finish()
+            })
+    })?;
+
+    Ok(())
+}
+
+// every component as a {chr}_{i}.bed entry of one tar (gzipped when the
+// path ends in .gz), entries are built in memory and streamed in
+pub fn tarwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    contents: DashMap<String, Vec<Vec<GenePred>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let compress = file.as_ref().extension().is_some_and(|x| x == "gz");
+    let mut archive = tar::Builder::new(BedOutput::create(&file, compress)?);
+
+    let mut contents = contents.into_iter().collect::<Vec<_>>();
+    contents.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    for (chr, buckets) in contents {
+        for (i, bucket) in buckets.iter().enumerate() {
+            let mut data = Vec::new();
+            for tx in bucket {
+                writeln!(data, "{}", tx.line())?;
+            }
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append_data(&mut header, format!("{}_{}.bed", chr, i), &data[..])?;
+        }
+    }
+
+    // finishes the tar stream, then the gzip one
+    archive.into_inner()?.finish()?;
 
     Ok(())
 }
@@ -382,30 +1983,57 @@ pub fn compwriter<T: AsRef<Path> + Debug + Sync>(
 #[derive(Debug, Clone)]
 struct UnionFind {
     parent: Vec<usize>,
+    // members under each root, only meaningful at roots
+    size: Vec<usize>,
 }
 
 impl UnionFind {
     fn new(n: usize) -> Self {
         Self {
             parent: (0..n).collect(),
+            size: vec![1; n],
         }
     }
 
     #[inline(always)]
     fn find(&mut self, x: usize) -> usize {
-        if self.parent[x] != x {
-            self.parent[x] = self.find(self.parent[x]);
+        // iterative so long merge chains cannot overflow the stack
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut x = x;
+        while self.parent[x] != root {
+            let next = self.parent[x];
+            self.parent[x] = root;
+            x = next;
         }
-        self.parent[x]
+
+        root
     }
 
     #[inline(always)]
     fn union(&mut self, x: usize, y: usize) {
-        let root_x = self.find(x);
-        let root_y = self.find(y);
-        if root_x != root_y {
-            self.parent[root_y] = root_x;
+        let mut root_x = self.find(x);
+        let mut root_y = self.find(y);
+        if root_x == root_y {
+            return;
+        }
+
+        // smaller tree under the larger one
+        if self.size[root_x] < self.size[root_y] {
+            std::mem::swap(&mut root_x, &mut root_y);
         }
+        self.parent[root_y] = root_x;
+        self.size[root_x] += self.size[root_y];
+    }
+
+    #[allow(dead_code)]
+    #[inline(always)]
+    fn size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
     }
 }
 
@@ -434,6 +2062,13 @@ mod tests {
         assert_eq!(res.len(), 1);
     }
 
+    #[test]
+    fn test_packbed_with_missing_file() {
+        let res = packbed_with(vec!["does_not_exist.bed"], &PackConfig::default());
+
+        assert!(res.is_err());
+    }
+
     #[test]
     fn test_packbed_with_simple_bed_gz() {
         use std::process::Command;
@@ -470,6 +2105,1461 @@ mod tests {
         assert_eq!(res.len(), 1);
     }
 
+    #[test]
+    fn test_chrom_summary_two_chroms() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "#header\ns8\t100\t200\tread1\t0\t-\t110\t190\t0\t3\t20,20,20,\t0,30,60,\ns9\t100\t200\tread2\t0\t+\t110\t190\t0\t3\t20,20,20,\t0,30,60,\ns8\t300\t400\tread3\t0\t+\t310\t390\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let summary = chrom_summary(vec![file.path()]).unwrap();
+
+        assert_eq!(summary, vec![("s8".to_string(), 2), ("s9".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_component_ids_stable_through_bin() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t500\t600\tread3\t0\t+\t510\t590\t0\t1\t100,\t0,\ns8\t100\t200\tread1\t0\t+\t110\t190\t0\t3\t20,20,20,\t0,30,60,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t2\t50,50,\t0,100,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let first = buckets.get("s8").unwrap()[0].clone();
+        let names = first.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["read1", "read2"]);
+
+        let bin = NamedTempFile::with_suffix(".bin").unwrap();
+        binwriter(bin.path(), buckets).unwrap();
+        let contents = binreader(bin.path()).unwrap();
+
+        assert_eq!(contents["s8"][0], first);
+        assert_eq!(contents["s8"][1][0].name, "read3");
+    }
+
+    #[test]
+    fn test_chunked_bin_round_trip_and_v1_fallback() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t500\t600\tread2\t0\t+\t510\t590\t0\t1\t100,\t0,\ns9\t100\t200\tread3\t0\t-\t110\t190\t0\t1\t100,\t0,\ns10\t100\t400\tread4\t0\t+\t110\t390\t0\t2\t100,100,\t0,200,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let expected = buckets
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect::<ComponentMap>();
+
+        let bin = NamedTempFile::with_suffix(".bin").unwrap();
+        binwriter(bin.path(), buckets).unwrap();
+        let contents = binreader(bin.path()).unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents, expected);
+
+        // version 1 bodies are a single msgpack map
+        let version = env!("CARGO_PKG_VERSION");
+        let mut v1 = b"PKBD".to_vec();
+        v1.extend(1u16.to_le_bytes());
+        v1.push(version.len() as u8);
+        v1.extend(version.as_bytes());
+        v1.extend(encode::to_vec(&expected).unwrap());
+        assert_eq!(binreader_from(&v1[..]).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_zstd_bin_round_trip() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns9\t100\t200\tread3\t0\t-\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let plain = NamedTempFile::with_suffix(".bin").unwrap();
+        let compressed = NamedTempFile::with_suffix(".bin.zst").unwrap();
+        binwriter(
+            plain.path(),
+            packbed(vec![file.path()], false, false, false).unwrap(),
+        )
+        .unwrap();
+        binwriter(
+            compressed.path(),
+            packbed(vec![file.path()], false, false, false).unwrap(),
+        )
+        .unwrap();
+
+        let bytes = std::fs::read(compressed.path()).unwrap();
+        assert_eq!(bytes[..4], ZSTD_MAGIC);
+        assert_eq!(
+            binreader(compressed.path()).unwrap(),
+            binreader(plain.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bin_header_is_validated() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(file, "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,").unwrap();
+
+        let bin = NamedTempFile::with_suffix(".bin").unwrap();
+        binwriter(
+            bin.path(),
+            packbed(vec![file.path()], false, false, false).unwrap(),
+        )
+        .unwrap();
+
+        let bytes = std::fs::read(bin.path()).unwrap();
+        assert_eq!(&bytes[..4], b"PKBD");
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), BIN_FORMAT_VERSION);
+        assert_eq!(
+            binreader_from(&bytes[..]).unwrap()["s8"][0][0].name,
+            "read1"
+        );
+
+        // a headerless msgpack body, as written before versioning
+        let err = binreader_from(&bytes[7 + bytes[6] as usize..]).unwrap_err();
+        assert!(err.to_string().contains("not a packbed .bin file"));
+
+        let mut future = bytes.clone();
+        future[4..6].copy_from_slice(&9u16.to_le_bytes());
+        let err = binreader_from(&future[..]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("unsupported .bin format version 9"));
+    }
+
+    #[test]
+    fn test_colorize_multi_only_keeps_singletons() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t1,2,3\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t1,2,3\t1\t150,\t0,\ns8\t500\t600\tread3\t0\t+\t510\t590\t1,2,3\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let config = PackConfig {
+            colorize_multi_only: true,
+            ..PackConfig::new(false, false, true)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        let comps = buckets.get("s8").unwrap();
+
+        assert_eq!(comps[1].len(), 1);
+        assert_eq!(comps[1][0].rgb, "1,2,3");
+        assert!(comps[1][0].line().contains("\t1,2,3\t"));
+
+        assert_eq!(comps[0].len(), 2);
+        assert!(comps[0].iter().all(|x| x.rgb != "1,2,3"));
+        assert_eq!(comps[0][0].rgb, comps[0][1].rgb);
+    }
+
+    #[test]
+    fn test_scale_score_min_max() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t2.5\t+\t110\t190\t0\t1\t100,\t0,\ns8\t500\t600\tread2\t52.5\t+\t510\t590\t0\t1\t100,\t0,\ns9\t100\t200\tread3\t12.5\t-\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let config = PackConfig {
+            scale_score: true,
+            ..PackConfig::new(false, false, false)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+
+        let score = |chr: &str, name: &str| {
+            buckets
+                .get(chr)
+                .unwrap()
+                .iter()
+                .flatten()
+                .find(|x| x.name == name)
+                .unwrap()
+                .line()
+                .split('\t')
+                .nth(4)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(score("s8", "read1"), "0");
+        assert_eq!(score("s8", "read2"), "1000");
+        assert_eq!(score("s9", "read3"), "200");
+    }
+
+    #[test]
+    fn test_membership_table_shared_index() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t500\t600\tread3\t0\t+\t510\t590\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let table = membership_table(&buckets);
+        let index = |name: &str| {
+            table
+                .lines()
+                .map(|x| x.split('\t').collect::<Vec<_>>())
+                .find(|x| x[0] == name)
+                .map(|x| (x[1].to_string(), x[2].to_string()))
+                .unwrap()
+        };
+
+        assert_eq!(table.lines().count(), 3);
+        assert_eq!(index("read1"), index("read2"));
+        assert_ne!(index("read1"), index("read3"));
+    }
+
+    #[test]
+    fn test_strict_duplicated_blocks() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t300\tread1\t0\t+\t100\t300\t0\t3\t50,50,50,\t0,0,150,\ns8\t500\t600\tread2\t0\t+\t510\t590\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        assert_eq!(buckets.get("s8").unwrap()[0][0].exon_count, 2);
+
+        let config = PackConfig {
+            strict: true,
+            ..PackConfig::new(false, false, false)
+        };
+        assert!(packbed_with(vec![file.path()], &config).is_err());
+    }
+
+    #[test]
+    fn test_packbed_strict_reports_first_bad_line() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "# header\ns8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t100\t200\tread2\t0\t*\t110\t190\t0\t1\t100,\t0,\ns8\t100\t200\tread3\t0\t+\t110\n"
+        )
+        .unwrap();
+
+        let config = PackConfig::new(false, false, false);
+        let contents = reader(file.path()).unwrap();
+        assert_eq!(
+            parse_errors(&contents, &config),
+            vec![
+                (3, ParseError::BadStrand('*')),
+                (4, ParseError::MissingField("cds_end"))
+            ]
+        );
+
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        assert_eq!(buckets.get("s8").unwrap().len(), 1);
+
+        let err = packbed_strict(vec![file.path()], &config).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: Strand * is not + or -");
+    }
+
+    #[test]
+    fn test_parse_bed12_file() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t2\t20,20,\t0,80,\ns9\t300\t600\tread2\t0\t-\t330\t550\t0\t2\t50,100,\t0,200,\n"
+        )
+        .unwrap();
+
+        let tracks = parse_bed12_file(file.path(), false).unwrap();
+        let mut keys = tracks.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+
+        assert_eq!(keys, vec!["s8", "s9"]);
+        assert_eq!(tracks["s8"].len(), 1);
+        assert_eq!(tracks["s9"][0].exons, vec![(300, 350), (500, 600)]);
+
+        let clipped = parse_bed12_file(file.path(), true).unwrap();
+        assert_eq!(clipped["s8"][0].exons, vec![(110, 120), (180, 190)]);
+    }
+
+    #[test]
+    fn test_parse_stats_count_valid_lines() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "# header\ns8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\nnot a record\ns8\t150\t300\tread2\t0\t-\t160\t290\t0\t1\t150,\t0,\ns9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,\n"
+        )
+        .unwrap();
+
+        let config = PackConfig::new(false, false, false);
+        let (buckets, stats) = packbed_with_stats(vec![file.path()], &config).unwrap();
+
+        assert_eq!(
+            stats,
+            ParseStats {
+                records: 3,
+                chromosomes: 2
+            }
+        );
+        assert_eq!(buckets.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_report_counts_garbage_lines() {
+        let mut contents = String::new();
+        for i in 0..4 {
+            contents.push_str(&format!(
+                "s8\t{}\t{}\tread{}\t0\t+\t{}\t{}\t0\t1\t100,\t0,\n",
+                i * 1000,
+                i * 1000 + 100,
+                i,
+                i * 1000,
+                i * 1000 + 100
+            ));
+            contents.push_str(&format!("garbage line {}\n", i));
+        }
+        contents.push_str("s8\t0\t100\tbad\t0\t?\t0\t100\t0\t1\t100,\t0,\n\n");
+
+        let config = PackConfig::new(false, false, false);
+        let (tracks, report) = parse_tracks_with_report(&contents, &config).unwrap();
+
+        assert_eq!(tracks["s8"].len(), 4);
+        assert_eq!(report.lines, 9);
+        assert_eq!(report.skipped, 5);
+        assert_eq!(report.reasons[&ParseError::MissingField("tx_start")], 4);
+        assert_eq!(report.reasons[&ParseError::BadStrand('?')], 1);
+        assert_eq!(report.samples.len(), 5);
+    }
+
+    #[test]
+    fn test_one_based_output_round_trip() {
+        let line = "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t2\t20,20,\t0,80,";
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(file, "{}", line).unwrap();
+
+        let out = NamedTempFile::with_suffix(".bed").unwrap();
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        bedwriter_with(out.path(), buckets, true, false).unwrap();
+
+        let written = std::fs::read_to_string(out.path()).unwrap();
+        let fields = written.trim_end().split('\t').collect::<Vec<_>>();
+        assert_eq!(fields[1], "101");
+        assert_eq!(fields[6], "111");
+        assert_eq!(fields[2], "200");
+
+        let config = PackConfig {
+            one_based_input: true,
+            ..PackConfig::new(false, false, false)
+        };
+        let back = packbed_with(vec![out.path()], &config).unwrap();
+        let tx = &back.get("s8").unwrap()[0][0];
+        assert_eq!((tx.start, tx.end), (100, 200));
+        assert_eq!(tx.exons, vec![(100, 120), (180, 200)]);
+        assert_eq!(tx.line(), line);
+    }
+
+    #[test]
+    fn test_overlap_modes_on_same_input() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t500\tread1\t0\t+\t150\t450\t0\t2\t100,100,\t0,300,\ns8\t250\t350\tread2\t0\t+\t260\t340\t0\t1\t100,\t0,\ns8\t420\t700\tread3\t0\t+\t460\t700\t0\t2\t60,100,\t0,180,"
+        )
+        .unwrap();
+
+        let count = |mode| {
+            let config = PackConfig::with_mode(mode, false);
+            assert_eq!(config.overlap_mode, mode);
+            assert_eq!(
+                PackConfig::new(mode == OverlapMode::Cds, mode == OverlapMode::Exon, false)
+                    .overlap_mode,
+                mode
+            );
+            packbed_with(vec![file.path()], &config)
+                .unwrap()
+                .get("s8")
+                .unwrap()
+                .len()
+        };
+
+        // read2 sits in the read1 intron, read3 only shares read1 UTR bases
+        assert_eq!(count(OverlapMode::Boundary), 1);
+        assert_eq!(count(OverlapMode::Exon), 2);
+        assert_eq!(count(OverlapMode::Cds), 3);
+
+        assert_eq!("cds".parse::<OverlapMode>(), Ok(OverlapMode::Cds));
+        assert!("intron".parse::<OverlapMode>().is_err());
+    }
+
+    #[test]
+    fn test_input_order_within_components() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t300\t400\tread1\t0\t+\t310\t390\t0\t1\t100,\t0,\n# comment\ns8\t100\t350\tread2\t0\t+\t110\t340\t0\t1\t250,\t0,\ns8\t200\t320\tread3\t0\t+\t210\t310\t0\t1\t120,\t0,"
+        )
+        .unwrap();
+
+        let names = |order_mode, chunk_size| {
+            let config = PackConfig {
+                order_mode,
+                chunk_size,
+                ..PackConfig::new(false, false, false)
+            };
+            let buckets = packbed_with(vec![file.path()], &config).unwrap();
+            let comps = buckets.get("s8").unwrap();
+            assert_eq!(comps.len(), 1);
+            comps[0].iter().map(|x| x.name.clone()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(OrderMode::Coordinate, None),
+            vec!["read2", "read3", "read1"]
+        );
+        assert_eq!(
+            names(OrderMode::Input, None),
+            vec!["read1", "read2", "read3"]
+        );
+        assert_eq!(
+            names(OrderMode::Input, Some(2)),
+            vec!["read1", "read2", "read3"]
+        );
+    }
+
+    #[test]
+    fn test_coding_components_only() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t200\t200\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t300\t300\t0\t1\t150,\t0,\ns8\t500\t600\tread3\t0\t+\t600\t600\t0\t1\t100,\t0,\ns8\t550\t700\tread4\t0\t+\t560\t690\t0\t1\t150,\t0,"
+        )
+        .unwrap();
+
+        let config = PackConfig {
+            coding_components_only: true,
+            ..PackConfig::new(false, false, false)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        let comps = buckets.get("s8").unwrap();
+
+        assert_eq!(comps.len(), 1);
+        let mut names = comps[0].iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, vec!["read3", "read4"]);
+    }
+
+    #[test]
+    fn test_min_component_size_drops_singletons() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t500\t600\tread3\t0\t+\t510\t590\t0\t1\t100,\t0,\ns9\t100\t200\tread4\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let pack = |min_component_size| {
+            let config = PackConfig {
+                min_component_size,
+                ..PackConfig::new(false, false, false)
+            };
+            packbed_with(vec![file.path()], &config).unwrap()
+        };
+
+        let kept = pack(1);
+        assert_eq!(kept.get("s8").unwrap().len(), 2);
+        assert_eq!(kept.get("s9").unwrap().len(), 1);
+
+        let dropped = pack(2);
+        assert_eq!(dropped.get("s8").unwrap().len(), 1);
+        assert_eq!(dropped.get("s8").unwrap()[0].len(), 2);
+        assert!(dropped.get("s9").is_none());
+    }
+
+    #[test]
+    fn test_dedup_keeps_one_of_identical_records() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t400\tread3\t0\t+\t110\t390\t0\t2\t100,100,\t0,200,\ns8\t100\t400\tread1\t0\t+\t110\t390\t0\t2\t100,100,\t0,200,\ns8\t100\t400\tread2\t0\t+\t110\t390\t0\t2\t100,100,\t0,200,\ns8\t100\t400\tread4\t0\t+\t110\t390\t0\t2\t50,100,\t0,200,"
+        )
+        .unwrap();
+
+        let config = PackConfig {
+            dedup: true,
+            ..PackConfig::new(false, false, false)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        let comps = buckets.get("s8").unwrap();
+
+        let mut names = comps[0].iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, vec!["read1", "read4"]);
+    }
+
+    #[test]
+    fn test_chunk_size_same_output() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        for i in 0..50u64 {
+            writeln!(
+                file,
+                "s{}\t{}\t{}\tread{}\t0\t{}\t{}\t{}\t0\t1\t{},\t0,",
+                i % 3,
+                i * 70,
+                i * 70 + 100 + i,
+                i,
+                if i % 2 == 0 { '+' } else { '-' },
+                i * 70,
+                i * 70 + 100 + i,
+                100 + i
+            )
+            .unwrap();
+        }
+
+        let lines = |chunk_size: Option<usize>| {
+            let config = PackConfig {
+                chunk_size,
+                ..PackConfig::new(false, false, false)
+            };
+            let out = NamedTempFile::with_suffix(".bed").unwrap();
+            sorted_bedwriter(
+                out.path(),
+                packbed_with(vec![file.path()], &config).unwrap(),
+            )
+            .unwrap();
+            std::fs::read_to_string(out.path()).unwrap()
+        };
+
+        let expected = lines(None);
+        assert_eq!(expected.lines().count(), 50);
+        for chunk_size in [1, 7, 1000] {
+            assert_eq!(lines(Some(chunk_size)), expected);
+        }
+    }
+
+    #[test]
+    fn test_detailwriter_schema_matches_columns() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,"
+        )
+        .unwrap();
+
+        let out = NamedTempFile::with_suffix(".bed").unwrap();
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        detailwriter(out.path(), buckets, "test").unwrap();
+
+        let mut schema = out.path().as_os_str().to_owned();
+        schema.push(".as");
+        let schema = std::fs::read_to_string(schema).unwrap();
+        let fields = schema.lines().filter(|x| x.ends_with("\"")).count() - 1;
+
+        let written = std::fs::read_to_string(out.path()).unwrap();
+        for line in written.lines() {
+            assert_eq!(line.split('\t').count(), fields);
+        }
+        assert!(written.lines().all(|x| x.ends_with("\ts8_0\t2\ttest")));
+    }
+
+    #[test]
+    fn test_plain_text_named_gz() {
+        let mut file = NamedTempFile::with_suffix(".bed.gz").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        assert_eq!(buckets.get("s8").unwrap()[0].len(), 2);
+    }
+
+    #[test]
+    fn test_exon_catalog_shared_exons() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t500\tread1\t0\t+\t100\t500\t0\t3\t50,50,50,\t0,200,350,\ns8\t100\t500\tread2\t0\t+\t100\t500\t0\t2\t50,50,\t0,350,\ns8\t280\t500\tread3\t0\t+\t280\t500\t0\t2\t60,50,\t0,170,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let comps = buckets.get("s8").unwrap();
+        assert_eq!(comps.len(), 1);
+
+        let catalog = exon_catalog(&comps[0]);
+        assert_eq!(catalog, vec![(100, 150), (280, 350), (450, 500)]);
+
+        let out = NamedTempFile::with_suffix(".bed").unwrap();
+        drop(comps);
+        exoncatalogwriter(out.path(), &buckets).unwrap();
+        let written = std::fs::read_to_string(out.path()).unwrap();
+        assert_eq!(written.lines().count(), 3);
+        assert!(written.lines().last().unwrap().contains("\tcomp1.exon3\t"));
+    }
+
+    #[test]
+    fn test_compwriter_returns_write_errors() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(file, "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,").unwrap();
+
+        // a plain file where the component subdirectory should go
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("comp_s8_0"), "").unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        assert!(compwriter_with(buckets, dir.path(), true, None, false, true).is_err());
+    }
+
+    #[test]
+    fn test_compwriter_max_files_overflow() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t500\t600\tread3\t0\t+\t510\t590\t0\t1\t100,\t0,\ns9\t100\t200\tread4\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        compwriter_with(buckets, dir.path(), false, Some(1), false, false).unwrap();
+
+        let mut files = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort_unstable();
+        assert_eq!(files, vec!["overflow.bed", "s8_0.bed"]);
+
+        let overflow = std::fs::read_to_string(dir.path().join("overflow.bed")).unwrap();
+        assert_eq!(overflow.lines().count(), 2);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("s8_0.bed"))
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_merge_nearby_components() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t300\t400\tread2\t0\t+\t310\t390\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        assert_eq!(buckets.get("s8").unwrap().len(), 2);
+
+        let merged = merge_nearby_components(buckets.clone(), 50, false);
+        assert_eq!(merged.get("s8").unwrap().len(), 2);
+
+        let merged = merge_nearby_components(buckets, 200, false);
+        assert_eq!(merged.get("s8").unwrap().len(), 1);
+        assert_eq!(merged.get("s8").unwrap()[0].len(), 2);
+    }
+
+    #[test]
+    fn test_min_overlap_boundary() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        // read1 and read2 share 10 bases
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t190\t300\tread2\t0\t+\t200\t290\t0\t1\t110,\t0,"
+        )
+        .unwrap();
+
+        let pack = |min_overlap| {
+            let config = PackConfig {
+                min_overlap,
+                ..PackConfig::new(false, true, false)
+            };
+            packbed_with(vec![file.path()], &config)
+                .unwrap()
+                .get("s8")
+                .unwrap()
+                .len()
+        };
+
+        assert_eq!(pack(0), 1);
+        assert_eq!(pack(10), 1);
+        assert_eq!(pack(11), 2);
+    }
+
+    #[test]
+    fn test_reciprocal_overlap_chain() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        // A-B and B-C share 60 of 100 bases, A-C only 20
+        write!(
+            file,
+            "s8\t100\t200\treadA\t0\t+\t100\t200\t0\t1\t100,\t0,\ns8\t140\t240\treadB\t0\t+\t140\t240\t0\t1\t100,\t0,\ns8\t180\t280\treadC\t0\t+\t180\t280\t0\t1\t100,\t0,\ns8\t1000\t1100\treadD\t0\t+\t1000\t1100\t0\t1\t100,\t0,\ns8\t1090\t1190\treadE\t0\t+\t1090\t1190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let pack = |overlap_type| {
+            let config = PackConfig {
+                overlap_type,
+                ..PackConfig::new(false, true, false)
+            };
+            let buckets = packbed_with(vec![file.path()], &config).unwrap();
+            let mut sizes = buckets
+                .get("s8")
+                .unwrap()
+                .iter()
+                .map(|x| x.len())
+                .collect::<Vec<_>>();
+            sizes.sort_unstable();
+            sizes
+        };
+
+        assert_eq!(pack(OverlapType::Any), vec![2, 3]);
+        // the chain closes over A-C, D-E (10%) splits
+        assert_eq!(pack("reciprocal:0.5".parse().unwrap()), vec![1, 1, 3]);
+        assert_eq!(pack(OverlapType::Reciprocal(0.7)), vec![1, 1, 1, 1, 1]);
+
+        assert!("reciprocal:0".parse::<OverlapType>().is_err());
+        assert!("reciprocal:1.5".parse::<OverlapType>().is_err());
+        assert_eq!(
+            "reciprocal:1".parse::<OverlapType>(),
+            Ok(OverlapType::Reciprocal(1.0))
+        );
+    }
+
+    #[test]
+    fn test_reciprocal_overlap_keeps_strands_apart() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t100\t200\t0\t1\t100,\t0,\ns8\t100\t200\tread2\t0\t-\t100\t200\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let config = PackConfig {
+            overlap_type: OverlapType::Reciprocal(0.5),
+            ..PackConfig::new(false, true, false)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        assert_eq!(buckets.get("s8").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_intron_overlap_type() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        // read1/read2 overlap in exons with different introns, read3 shares
+        // read1's 200-300 intron
+        write!(
+            file,
+            "s8\t100\t400\tread1\t0\t+\t100\t400\t0\t2\t100,100,\t0,200,\ns8\t150\t500\tread2\t0\t+\t150\t500\t0\t2\t100,100,\t0,250,\ns8\t180\t450\tread3\t0\t+\t180\t450\t0\t2\t20,150,\t0,120,"
+        )
+        .unwrap();
+
+        let config = PackConfig {
+            overlap_type: "intron".parse().unwrap(),
+            ..PackConfig::new(false, true, false)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        let mut comps = buckets
+            .get("s8")
+            .unwrap()
+            .iter()
+            .map(|x| {
+                let mut names = x.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+                names.sort_unstable();
+                names.join(",")
+            })
+            .collect::<Vec<_>>();
+        comps.sort_unstable();
+
+        assert_eq!(comps, vec!["read1,read3", "read2"]);
+    }
+
+    #[test]
+    fn test_same_strand_only() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t-\t160\t290\t0\t1\t150,\t0,"
+        )
+        .unwrap();
+
+        let spans = |a: &GenePred, b: &GenePred| {
+            let (a, b) = ((a.start, a.end), (b.start, b.end));
+            a.0 < b.1 && b.0 < a.1
+        };
+        let config = PackConfig {
+            overlap_fn: Some(OverlapPredicate::new(0, spans)),
+            ..Default::default()
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        assert_eq!(buckets.get("s8").unwrap().len(), 1);
+
+        let config = PackConfig {
+            same_strand_only: true,
+            ..config
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        assert_eq!(buckets.get("s8").unwrap().len(), 2);
+
+        let config = PackConfig {
+            same_strand_only: true,
+            ..PackConfig::new(false, true, false)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        assert_eq!(buckets.get("s8").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_sweep_matches_pairwise_exon_overlap_on_bridges() {
+        // read1 has one long exon bridging read2 and read3; read4 and read5
+        // only graze read3 and read1 by 5 bases
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t1000\tread1\t0\t+\t100\t1000\t0\t1\t900,\t0,\ns8\t150\t400\tread2\t0\t+\t150\t400\t0\t2\t50,50,\t0,200,\ns8\t800\t1200\tread3\t0\t+\t800\t1200\t0\t2\t100,100,\t0,300,\ns8\t1195\t1300\tread4\t0\t+\t1195\t1300\t0\t1\t105,\t0,\ns8\t995\t1050\tread5\t0\t+\t995\t1050\t0\t1\t55,\t0,"
+        )
+        .unwrap();
+
+        let min_overlap = 10;
+        let exon_overlap = move |a: &GenePred, b: &GenePred| {
+            a.exons.iter().any(|x| {
+                b.exons
+                    .iter()
+                    .any(|y| x.1.min(y.1).saturating_sub(x.0.max(y.0)) >= min_overlap)
+            })
+        };
+
+        let names = |config: &PackConfig| {
+            let buckets = packbed_with(vec![file.path()], config).unwrap();
+            let mut comps = buckets
+                .get("s8")
+                .unwrap()
+                .iter()
+                .map(|x| {
+                    let mut names = x.iter().map(|tx| tx.name.clone()).collect::<Vec<_>>();
+                    names.sort_unstable();
+                    names
+                })
+                .collect::<Vec<_>>();
+            comps.sort_unstable();
+            comps
+        };
+
+        let sweep = PackConfig {
+            min_overlap,
+            ..PackConfig::with_mode(OverlapMode::Exon, false)
+        };
+        let pairwise = PackConfig {
+            overlap_fn: Some(OverlapPredicate::new(0, exon_overlap)),
+            ..sweep.clone()
+        };
+
+        assert_eq!(names(&sweep), names(&pairwise));
+        assert_eq!(
+            names(&sweep),
+            vec![
+                vec!["read1", "read2", "read3"],
+                vec!["read4"],
+                vec!["read5"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_packbed_with_custom_predicate() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tGENEA.1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tGENEB.1\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t180\t400\tGENEA.2\t0\t+\t190\t390\t0\t1\t220,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        assert_eq!(buckets.get("s8").unwrap().len(), 1);
+
+        let prefix = |x: &GenePred| x.name.split('.').next().unwrap().to_string();
+        let config = PackConfig {
+            overlap_fn: Some(OverlapPredicate::new(0, move |a, b| prefix(a) == prefix(b))),
+            ..Default::default()
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        let comps = buckets.get("s8").unwrap();
+
+        assert_eq!(comps.len(), 2);
+        assert_eq!(comps[0].len(), 2);
+        assert_eq!(comps[1][0].name, "GENEB.1");
+    }
+
+    #[test]
+    fn test_packbed_chrom_normalize_cross_file() {
+        let mut a = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(a, "chr1\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,").unwrap();
+        let mut b = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(b, "1\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,").unwrap();
+
+        let buckets = packbed(vec![a.path(), b.path()], false, false, false).unwrap();
+        assert_eq!(buckets.len(), 2);
+
+        let config = PackConfig {
+            chrom_normalize: Some(ChromNormalize::Strip("chr".into())),
+            ..Default::default()
+        };
+        let buckets = packbed_with(vec![a.path(), b.path()], &config).unwrap();
+
+        assert_eq!(buckets.len(), 1);
+        let comps = buckets.get("1").unwrap();
+        assert_eq!(comps.len(), 1);
+        assert_eq!(comps[0].len(), 2);
+        assert!(comps[0].iter().all(|x| x.line().starts_with("1\t")));
+    }
+
+    #[test]
+    fn test_tarwriter_entry_per_component() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t500\t600\tread3\t0\t+\t510\t590\t0\t1\t100,\t0,\ns9\t100\t200\tread4\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let n = buckets.iter().map(|x| x.value().len()).sum::<usize>();
+
+        let out = NamedTempFile::with_suffix(".tar.gz").unwrap();
+        tarwriter(out.path(), buckets).unwrap();
+
+        let decoder = MultiGzDecoder::new(File::open(out.path()).unwrap());
+        let mut archive = tar::Archive::new(decoder);
+        let names = archive
+            .entries()
+            .unwrap()
+            .map(|x| x.unwrap().path().unwrap().display().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(n, 3);
+        assert_eq!(names, vec!["s8_0.bed", "s8_1.bed", "s9_0.bed"]);
+    }
+
+    #[test]
+    fn test_extra_fields_survive_packing() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\tprotein_coding\tGENE1\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, true).unwrap();
+        let bin = NamedTempFile::with_suffix(".bin").unwrap();
+        binwriter(bin.path(), buckets).unwrap();
+
+        let contents = binreader(bin.path()).unwrap();
+        let comp = &contents.get("s8").unwrap()[0];
+        let read1 = comp.iter().find(|x| x.name == "read1").unwrap();
+        let read2 = comp.iter().find(|x| x.name == "read2").unwrap();
+
+        assert_eq!(read1.extra, vec!["protein_coding", "GENE1"]);
+        assert!(read2.extra.is_empty());
+    }
+
+    #[test]
+    fn test_topwriter_keeps_largest_component() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t500\t600\tread3\t0\t+\t510\t590\t0\t1\t100,\t0,\ns9\t100\t200\tread4\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let top = top_components(&buckets, 1);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].0, "s8");
+
+        let out = NamedTempFile::with_suffix(".bed").unwrap();
+        topwriter(out.path(), &buckets, 1).unwrap();
+
+        let contents = std::fs::read_to_string(out.path()).unwrap();
+        let mut names = contents
+            .lines()
+            .filter(|x| !x.starts_with('#'))
+            .map(|x| x.split('\t').nth(3).unwrap())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+
+        assert!(contents.starts_with("# s8_"));
+        assert_eq!(names, vec!["read1", "read2"]);
+    }
+
+    #[test]
+    fn test_long_single_line_is_rejected() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        file.write_all(&vec![b'a'; 10 << 20]).unwrap();
+
+        let err = packbed_with(vec![file.path()], &PackConfig::default()).unwrap_err();
+        assert!(err.to_string().contains("not newline-delimited"));
+    }
+
+    #[test]
+    fn test_union_find_deep_chain() {
+        let n = 1_000_000;
+        let mut uf = UnionFind::new(n);
+        // union by size would flatten the chain, so link parents by hand
+        for i in 0..n - 1 {
+            uf.parent[i] = i + 1;
+        }
+
+        assert_eq!(uf.find(0), n - 1);
+        assert_eq!(uf.parent[0], n - 1);
+        assert_eq!(uf.find(n / 2), n - 1);
+    }
+
+    #[test]
+    fn test_union_find_by_size() {
+        let mut uf = UnionFind::new(5);
+        uf.union(0, 1);
+        uf.union(0, 2);
+        // the singleton goes under the larger tree whatever the argument order
+        uf.union(3, 0);
+
+        assert_eq!(uf.find(3), uf.find(0));
+        assert_eq!(uf.find(0), 0);
+        assert_eq!(uf.size(3), 4);
+        assert_eq!(uf.size(4), 1);
+    }
+
+    #[test]
+    fn test_records_without_blocks_are_singletons() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t0\t\t\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t0\t\t"
+        )
+        .unwrap();
+
+        // exon overlap leaves the sweep without a single interval
+        let buckets = packbed(vec![file.path()], false, true, false).unwrap();
+        let comps = buckets.get("s8").unwrap();
+
+        assert_eq!(comps.len(), 2);
+        assert!(comps.iter().all(|x| x.len() == 1 && x[0].exons.is_empty()));
+    }
+
+    #[test]
+    fn test_packbed_from_str() {
+        let buckets = packbed_from_str(
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t500\t600\tread2\t0\t+\t510\t590\t0\t1\t100,\t0,\n",
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets.get("s8").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_get_component_bad_hints() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(file, "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,").unwrap();
+        let bed = || vec![file.path().to_path_buf()];
+
+        let err = get_component(
+            bed(),
+            Some(vec![("s9".to_string(), vec![0])]),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("chromosome s9 not found"));
+
+        let err = get_component(
+            bed(),
+            Some(vec![("s8".to_string(), vec![0, 3])]),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("component 3 out of range"));
+    }
+
+    #[test]
+    fn test_get_component_without_hint_writes_everything() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t500\t600\tread2\t0\t+\t510\t590\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let out = NamedTempFile::with_suffix(".bed").unwrap();
+        get_component(
+            vec![file.path().to_path_buf()],
+            None,
+            Some(out.path().to_path_buf()),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let written = std::fs::read_to_string(out.path()).unwrap();
+        let mut names = written
+            .lines()
+            .map(|x| x.split('\t').nth(3).unwrap())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, vec!["read1", "read2", "read3"]);
+
+        let empty = NamedTempFile::with_suffix(".bed").unwrap();
+        get_component(
+            vec![empty.path().to_path_buf()],
+            None,
+            Some(out.path().to_path_buf()),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(std::fs::read_to_string(out.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_neighbor_aware_colors_differ() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        for i in 0..25 {
+            writeln!(
+                file,
+                "s8\t{}\t{}\tread{}\t0\t+\t{}\t{}\t0\t1\t100,\t0,",
+                i * 200,
+                i * 200 + 100,
+                i,
+                i * 200,
+                i * 200 + 100
+            )
+            .unwrap();
+        }
+
+        let pack = |color_mode, color_seed| {
+            let config = PackConfig {
+                color_mode,
+                color_seed,
+                ..PackConfig::new(false, false, true)
+            };
+            let buckets = packbed_with(vec![file.path()], &config).unwrap();
+            let mut comps = buckets
+                .get("s8")
+                .unwrap()
+                .iter()
+                .map(|x| ((x[0].start, x[0].end), x[0].rgb.clone()))
+                .collect::<Vec<_>>();
+            comps.sort_unstable();
+            comps.into_iter().map(|(_, rgb)| rgb).collect::<Vec<_>>()
+        };
+
+        let colors = pack(ColorMode::NeighborAware, None);
+        assert_eq!(colors.len(), 25);
+        assert!(colors.windows(2).all(|w| w[0] != w[1]));
+
+        assert_eq!(
+            pack(ColorMode::Random, Some(7)),
+            pack(ColorMode::Random, Some(7))
+        );
+        assert_eq!(pack(ColorMode::Cyclic, None)[10], RGB[0]);
+    }
+
+    #[test]
+    fn test_custom_palette() {
+        assert!(parse_palette("255,0,0;0,256,0").is_err());
+        assert!(parse_palette("255,0;0,0,0").is_err());
+        assert!(parse_palette("").is_err());
+
+        let palette = parse_palette("255,0,0;0,255,0;0,0,255").unwrap();
+        assert_eq!(palette.len(), 3);
+
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        for i in 0..5 {
+            writeln!(
+                file,
+                "s8\t{}\t{}\tread{}\t0\t+\t{}\t{}\t0\t1\t100,\t0,",
+                i * 200,
+                i * 200 + 100,
+                i,
+                i * 200,
+                i * 200 + 100
+            )
+            .unwrap();
+        }
+
+        let config = PackConfig {
+            color_mode: ColorMode::Cyclic,
+            palette: Some(palette),
+            ..PackConfig::new(false, false, true)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        let colors = buckets
+            .get("s8")
+            .unwrap()
+            .iter()
+            .map(|x| x[0].rgb.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            colors,
+            vec!["255,0,0", "0,255,0", "0,0,255", "255,0,0", "0,255,0"]
+        );
+    }
+
+    #[test]
+    fn test_collapse_components_flattens_exons() {
+        let component = [
+            "s8\t100\t600\tread1\t0\t+\t100\t600\t0\t3\t100,100,100,\t0,200,400,",
+            "s8\t150\t700\tread2\t0\t+\t150\t700\t0\t3\t100,50,100,\t0,300,450,",
+        ]
+        .iter()
+        .map(|x| Bed12::parse(x, false).unwrap())
+        .collect::<Vec<_>>();
+
+        let buckets = Buckets::new();
+        buckets.insert("s8".to_string(), vec![component.clone()]);
+        let collapsed = collapse_components(buckets).unwrap();
+        let comps = collapsed.get("s8").unwrap();
+
+        assert_eq!(comps.len(), 1);
+        assert_eq!(comps[0].len(), 1);
+
+        let model = &comps[0][0];
+        assert_eq!(model.name, "comp_s8_0");
+        assert_eq!((model.start, model.end), (100, 700));
+        assert_eq!(model.exons, vec![(100, 250), (300, 400), (450, 700)]);
+        assert_eq!(model.introns, vec![(250, 300), (400, 450)]);
+
+        let mut mixed = component;
+        mixed[1].strand = '-';
+        let buckets = Buckets::new();
+        buckets.insert("s8".to_string(), vec![mixed]);
+        assert!(collapse_components(buckets).is_err());
+    }
+
+    #[test]
+    fn test_tag_components_round_trip() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t500\t600\tread2\t0\t+\t510\t590\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let buckets = tag_components(packbed(vec![file.path()], false, false, false).unwrap());
+        let out = NamedTempFile::with_suffix(".bed").unwrap();
+        bedwriter(out.path(), buckets).unwrap();
+
+        let mut records = std::fs::read_to_string(out.path())
+            .unwrap()
+            .lines()
+            .map(|x| Bed12::parse(x, false).unwrap())
+            .collect::<Vec<_>>();
+        records.sort_unstable_by_key(|x| x.start);
+
+        assert_eq!(records[0].name, "read1|comp_s8_0");
+        assert_eq!(records[1].name, "read2|comp_s8_1");
+        assert!(records[0]
+            .line()
+            .starts_with("s8\t100\t200\tread1|comp_s8_0\t0\t+\t110\t190"));
+    }
+
+    #[test]
+    fn test_sorted_bedwriter_with() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s9\t50\t150\tread5\t0\t+\t60\t140\t0\t1\t100,\t0,\ns8\t300\t400\tread3\t0\t-\t310\t390\t0\t1\t100,\t0,\ns8\t100\t500\tread1\t0\t+\t110\t490\t0\t1\t400,\t0,\ns8\t150\t250\tread2\t0\t+\t160\t240\t0\t1\t100,\t0,\ns8\t120\t130\tread4\t0\t-\t120\t130\t0\t1\t10,\t0,"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let out = NamedTempFile::with_suffix(".bed").unwrap();
+        bedwriter_with(out.path(), buckets, false, true).unwrap();
+
+        let contents = std::fs::read_to_string(out.path()).unwrap();
+        let records = contents
+            .lines()
+            .map(|x| {
+                let fields = x.split('\t').collect::<Vec<_>>();
+                (fields[0].to_string(), fields[1].parse::<u64>().unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(records.len(), 5);
+        assert!(records.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_read_from_plain_and_gzip_stream() {
+        let bed = "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t1000\t1100\tread3\t0\t-\t1010\t1090\t0\t1\t100,\t0,\n";
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bed.as_bytes()).unwrap();
+        let gz = encoder.finish().unwrap();
+
+        let config = PackConfig::new(false, false, false);
+        for input in [bed.as_bytes().to_vec(), gz] {
+            let contents = read_from(std::io::Cursor::new(input)).unwrap();
+            assert_eq!(contents, bed);
+
+            let buckets = buckerize(parse_tracks(&contents, &config).unwrap(), &config);
+            let comps = buckets.get("s8").unwrap();
+            assert_eq!(comps.len(), 2);
+            assert_eq!(comps.iter().map(|x| x.len()).max(), Some(2));
+        }
+
+        assert!(is_stdin("-"));
+        assert!(!is_stdin("./-"));
+    }
+
+    #[test]
+    fn test_packbed_reads_genepred_input() {
+        let mut file = NamedTempFile::with_suffix(".gp").unwrap();
+        write!(
+            file,
+            "tx1\ts8\t+\t100\t200\t100\t100\t1\t100,\t200,\ntx2\ts8\t+\t150\t300\t150\t150\t1\t150,\t300,\ntx3\ts8\t-\t1000\t1100\t1000\t1000\t1\t1000,\t1100,\n"
+        )
+        .unwrap();
+
+        let config = PackConfig {
+            input_format: InputFormat::GenePred,
+            ..PackConfig::new(false, false, false)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        let comps = buckets.get("s8").unwrap();
+
+        assert_eq!(comps.len(), 2);
+        assert_eq!(comps.iter().map(|x| x.len()).sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn test_packbed_reads_gtf_input() {
+        let mut file = NamedTempFile::with_suffix(".gtf").unwrap();
+        write!(
+            file,
+            "s8\tsrc\texon\t101\t200\t.\t+\t.\ttranscript_id \"tx1\";\ns8\tsrc\texon\t151\t300\t.\t+\t.\ttranscript_id \"tx2\";\ns8\tsrc\texon\t1001\t1100\t.\t+\t.\ttranscript_id \"tx3\";\n"
+        )
+        .unwrap();
+
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let comps = buckets.get("s8").unwrap();
+
+        assert_eq!(comps.len(), 2);
+        assert_eq!(comps.iter().map(|x| x.len()).max(), Some(2));
+    }
+
+    #[test]
+    fn test_bedwriter_to_buffer_matches_file() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t-\t160\t290\t0\t1\t150,\t0,\ns8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let mut buffer = Vec::new();
+        bedwriter_to(
+            &mut buffer,
+            packbed(vec![file.path()], false, false, false).unwrap(),
+            false,
+            true,
+        )
+        .unwrap();
+
+        let out = NamedTempFile::with_suffix(".bed").unwrap();
+        bedwriter_with(
+            out.path(),
+            packbed(vec![file.path()], false, false, false).unwrap(),
+            false,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(buffer, std::fs::read(out.path()).unwrap());
+        assert_eq!(String::from_utf8(buffer).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_jsonwriter_round_trip() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t-\t160\t290\t0\t1\t150,\t0,\ns9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let json = NamedTempFile::with_suffix(".json").unwrap();
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        let expected = buckets.clone().into_iter().collect::<HashMap<_, _>>();
+        jsonwriter(json.path(), buckets).unwrap();
+
+        let contents = std::fs::read_to_string(json.path()).unwrap();
+        let parsed: HashMap<String, Vec<Vec<GenePred>>> = serde_json::from_str(&contents).unwrap();
+
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed["s8"].len(), 2);
+    }
+
+    #[test]
+    fn test_gzip_bed_output_round_trip() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t-\t160\t290\t0\t1\t150,\t0,\ns9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let plain = NamedTempFile::with_suffix(".bed").unwrap();
+        let gz = NamedTempFile::with_suffix(".bed.gz").unwrap();
+        bedwriter_with(
+            plain.path(),
+            packbed(vec![file.path()], false, false, false).unwrap(),
+            false,
+            true,
+        )
+        .unwrap();
+        bedwriter_with(
+            gz.path(),
+            packbed(vec![file.path()], false, false, false).unwrap(),
+            false,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(std::fs::read(gz.path()).unwrap()[..2], [0x1f, 0x8b]);
+        assert_eq!(reader(gz.path()).unwrap(), reader(plain.path()).unwrap());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        compwriter_with(
+            packbed(vec![file.path()], false, false, false).unwrap(),
+            dir.path(),
+            false,
+            None,
+            false,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            reader(dir.path().join("s9_0.bed.gz")).unwrap(),
+            "s9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,\n"
+        );
+    }
+
     #[test]
     fn test_exonic_overlap_true() {
         let r = &BTreeSet::from([(10, 20), (30, 40), (50, 60)]);
@@ -477,7 +3567,7 @@ mod tests {
 
         let res = exonic_overlap(r, q);
 
-        assert_eq!(res, true);
+        assert!(res);
     }
 
     #[test]
@@ -487,6 +3577,6 @@ mod tests {
 
         let res = exonic_overlap(r, q);
 
-        assert_eq!(res, false);
+        assert!(!res);
     }
 }
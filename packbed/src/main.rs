@@ -1,6 +1,8 @@
 use packbed::*;
 
 use clap::{self, Parser, ValueEnum};
+use dashmap::DashMap;
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
@@ -18,18 +20,33 @@ struct Args {
         value_name = "PATHS",
         value_delimiter = ',',
         num_args = 1..,
-        help = "Paths to BED12 files delimited by comma"
+        help = "Paths to BED12 files delimited by comma, - reads stdin [.gtf/.gff/.gff3 are read as GTF/GFF3]"
     )]
     pub bed: Vec<PathBuf>,
 
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        help = "Only log errors",
+        conflicts_with = "verbose"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        short = 'v',
+        long = "verbose",
+        help = "Also log per-chromosome details"
+    )]
+    pub verbose: bool,
+
     #[arg(
         short = 'o',
         long = "output",
-        required = true,
+        required_unless_present = "list_chroms",
         value_name = "PATH",
-        help = "Path to output BED12 file [will interpret as dir if -t flag is set to comp]"
+        help = "Path to output BED12 file, - writes bed output to stdout, a .zst path zstd-compresses bin output [will interpret as dir if -t flag is set to comp]"
     )]
-    pub output: PathBuf,
+    pub output: Option<PathBuf>,
 
     #[arg(
         short = 't',
@@ -40,6 +57,15 @@ struct Args {
     )]
     pub threads: usize,
 
+    #[arg(
+        long = "input-format",
+        help = "Line format of the inputs, .gtf/.gff/.gff3 files are detected by extension; unstranded (.) BED6 records are packed as +",
+        value_name = "FORMAT",
+        value_enum,
+        default_value = "bed"
+    )]
+    pub input_format: InputChoice,
+
     #[arg(
         long = "type",
         help = "Type of output",
@@ -49,9 +75,21 @@ struct Args {
     )]
     pub out_type: TypeChoice,
 
+    #[arg(
+        long = "overlap_mode",
+        help = "Intervals that must overlap: boundary, exon or cds",
+        value_name = "MODE",
+        value_enum,
+        default_value = "boundary",
+        default_value_ifs = [("overlap_cds", "true", "cds"), ("overlap_exon", "true", "exon")],
+        conflicts_with_all = ["overlap_cds", "overlap_exon"]
+    )]
+    pub overlap_mode: ModeChoice,
+
+    // aliases that only feed the overlap_mode default above
     #[arg(
         long = "overlap_cds",
-        help = "Flag to overlap only cds regions",
+        help = "Flag to overlap only cds regions, same as --overlap_mode cds",
         value_name = "FLAG",
         default_value = "false",
         conflicts_with = "overlap_exon"
@@ -60,13 +98,64 @@ struct Args {
 
     #[arg(
         long = "overlap_exon",
-        help = "Flag to overlap only exon regions",
+        help = "Flag to overlap only exon regions, same as --overlap_mode exon",
         value_name = "FLAG",
         default_value = "false",
         conflicts_with = "overlap_cds"
     )]
     pub overlap_exon: bool,
 
+    #[arg(
+        long = "order",
+        help = "Member order inside each component: coordinate or input (as in the files)",
+        value_name = "ORDER",
+        value_enum,
+        default_value = "coordinate"
+    )]
+    pub order: OrderChoice,
+
+    #[arg(
+        long = "sorted",
+        help = "Flag to write records sorted by chromosome and genomic start [bed and comp output]",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub sorted: bool,
+
+    #[arg(
+        long = "compress",
+        help = "Flag to gzip every component file [comp output only, bed output follows a .gz extension]",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub compress: bool,
+
+    #[arg(
+        long = "tag_component",
+        help = "Flag to rename records to {name}|comp_{chr}_{i} in the output",
+        value_name = "FLAG",
+        default_value = "false",
+        conflicts_with = "spill_threshold"
+    )]
+    pub tag_component: bool,
+
+    #[arg(
+        long = "collapse",
+        help = "Flag to write one merged model per component, named comp_{chr}_{i}",
+        value_name = "FLAG",
+        default_value = "false",
+        conflicts_with = "spill_threshold"
+    )]
+    pub collapse: bool,
+
+    #[arg(
+        long = "overlap_type",
+        help = "Merge rule: any shared base, intron for a shared intron, or reciprocal:<f> for a shared fraction f of both transcripts",
+        value_name = "TYPE",
+        default_value = "any"
+    )]
+    pub overlap_type: OverlapType,
+
     #[arg(
         short = 's',
         long = "subdirs",
@@ -76,6 +165,20 @@ struct Args {
     )]
     pub subdirs: bool,
 
+    #[arg(
+        long = "max_files",
+        help = "Only write the N largest components as files and bundle the rest into overflow.bed [comp output only]",
+        value_name = "N"
+    )]
+    pub max_files: Option<usize>,
+
+    #[arg(
+        long = "regions",
+        help = "BED4 of named regions; writes {region}.bed files (and unassigned.bed) into --output as a dir",
+        value_name = "BED"
+    )]
+    pub regions: Option<PathBuf>,
+
     #[arg(
         long = "colorize",
         help = "Flag to colorize components in output BED file",
@@ -83,6 +186,358 @@ struct Args {
         default_value = "false"
     )]
     pub colorize: bool,
+
+    #[arg(
+        long = "colorize_multi_only",
+        help = "Flag to only colorize components with 2 or more members",
+        value_name = "FLAG",
+        default_value = "false",
+        requires = "colorize"
+    )]
+    pub colorize_multi_only: bool,
+
+    #[arg(
+        long = "color_mode",
+        help = "How components are colored: random, cyclic or neighbor (adjacent components differ)",
+        value_name = "MODE",
+        value_enum,
+        default_value = "random",
+        requires = "colorize"
+    )]
+    pub color_mode: ColorChoice,
+
+    #[arg(
+        long = "color_seed",
+        help = "Seed for --color_mode random, making colors reproducible",
+        value_name = "SEED",
+        requires = "colorize"
+    )]
+    pub color_seed: Option<u64>,
+
+    #[arg(
+        long = "palette",
+        help = "Colors to use instead of the built-in palette, as r,g,b;r,g,b;...",
+        value_name = "COLORS",
+        requires = "colorize"
+    )]
+    pub palette: Option<String>,
+
+    #[arg(
+        long = "scale-score",
+        help = "Flag to linearly rescale the score column into 0-1000",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub scale_score: bool,
+
+    #[arg(
+        long = "strict",
+        help = "Flag to fail on malformed records (e.g. duplicated exon blocks) instead of warning",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub strict: bool,
+
+    #[arg(
+        long = "one-based-output",
+        help = "Flag to write chromStart/thickStart + 1 [NON-STANDARD BED, bed output only]",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub one_based_output: bool,
+
+    #[arg(
+        long = "one-based-input",
+        help = "Flag to read inputs written with --one-based-output",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub one_based_input: bool,
+
+    #[arg(
+        long = "chrom",
+        help = "Only pack this chromosome; uses a .gzi next to chromosome-sorted bgzipped inputs",
+        value_name = "CHROM"
+    )]
+    pub chrom: Option<String>,
+
+    #[arg(
+        long = "dedup",
+        help = "Flag to keep only one of records sharing strand, span and exons",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub dedup: bool,
+
+    #[arg(
+        long = "coding_components_only",
+        help = "Flag to drop components without any coding member",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub coding_components_only: bool,
+
+    #[arg(
+        long = "chunk_size",
+        help = "Lines per parsing task [default: automatic rayon splitting]",
+        value_name = "LINES"
+    )]
+    pub chunk_size: Option<usize>,
+
+    #[arg(
+        long = "max_line_len",
+        help = "Reject inputs with a line longer than this many bytes [default: 1 MiB]",
+        value_name = "BYTES"
+    )]
+    pub max_line_len: Option<usize>,
+
+    #[arg(
+        long = "min_overlap",
+        help = "Minimum number of shared bases for two transcripts to merge",
+        value_name = "BASES",
+        default_value = "1"
+    )]
+    pub min_overlap: u64,
+
+    #[arg(
+        long = "min-size",
+        help = "Drop components with fewer transcripts than this",
+        value_name = "N",
+        default_value = "1"
+    )]
+    pub min_size: usize,
+
+    #[arg(
+        long = "same_strand_only",
+        help = "Flag to only merge overlapping transcripts on the same strand",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub same_strand_only: bool,
+
+    #[arg(
+        long = "warn_component_count",
+        help = "Warn when a chromosome produces more than this many components",
+        value_name = "N"
+    )]
+    pub warn_component_count: Option<usize>,
+
+    #[arg(
+        long = "duplicate_names",
+        help = "What to do with names shared by records on different chromosomes or distant loci",
+        value_name = "POLICY"
+    )]
+    pub duplicate_names: Option<PolicyChoice>,
+
+    #[arg(
+        long = "duplicate_distance",
+        help = "Records sharing a name further apart than this are distinct loci",
+        value_name = "BP",
+        default_value = "100000"
+    )]
+    pub duplicate_distance: u64,
+
+    #[arg(
+        long = "duplicate_report",
+        help = "Write flagged names and their loci to this TSV",
+        value_name = "PATH"
+    )]
+    pub duplicate_report: Option<PathBuf>,
+
+    #[arg(
+        long = "spill_threshold",
+        help = "Spill components of chromosomes with more records than this to temp files [bed output only]",
+        value_name = "RECORDS"
+    )]
+    pub spill_threshold: Option<usize>,
+
+    #[arg(
+        long = "list-chroms",
+        help = "Flag to print chromosomes and their record counts without packing",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub list_chroms: bool,
+
+    #[arg(
+        long = "timing",
+        help = "Path to write a timing TSV: total parse time and per-chromosome bucketize time",
+        value_name = "PATH"
+    )]
+    pub timing: Option<PathBuf>,
+
+    #[arg(
+        long = "streaming",
+        help = "Flag to parse inputs straight from memory-mapped files, for inputs too large to read whole",
+        value_name = "FLAG",
+        default_value = "false",
+        conflicts_with = "timing"
+    )]
+    pub streaming: bool,
+
+    #[arg(
+        long = "max_intron_len",
+        help = "Split transcripts at introns longer than this before packing",
+        value_name = "BP"
+    )]
+    pub max_intron_len: Option<u64>,
+
+    #[arg(
+        long = "fix",
+        help = "Flag to write a corrected BED12 to --output and a report of fixes to <output>.fixes.tsv without packing",
+        value_name = "FLAG",
+        default_value = "false"
+    )]
+    pub fix: bool,
+
+    #[arg(
+        long = "diff",
+        help = "Path to a previous .bin; writes added/removed/modified BEDs into --output as a dir",
+        value_name = "BIN"
+    )]
+    pub diff: Option<PathBuf>,
+
+    #[arg(
+        long = "introns",
+        help = "Also write introns with their splice-site class to this BED",
+        value_name = "PATH",
+        requires = "fasta"
+    )]
+    pub introns: Option<PathBuf>,
+
+    #[arg(
+        long = "fasta",
+        help = "Reference FASTA used to classify splice sites",
+        value_name = "FASTA"
+    )]
+    pub fasta: Option<PathBuf>,
+
+    #[arg(
+        long = "novel_introns",
+        help = "Also write introns absent from --reference to this BED",
+        value_name = "PATH",
+        requires = "reference"
+    )]
+    pub novel_introns: Option<PathBuf>,
+
+    #[arg(
+        long = "reference",
+        help = "Reference annotation BED12 used by --novel_introns",
+        value_name = "BED"
+    )]
+    pub reference: Option<PathBuf>,
+
+    #[arg(
+        long = "hotspots",
+        help = "Also write intervals covered by at least --min_components component spans to this BED",
+        value_name = "PATH"
+    )]
+    pub hotspots: Option<PathBuf>,
+
+    #[arg(
+        long = "min_components",
+        help = "Minimum number of overlapping components for --hotspots",
+        value_name = "K",
+        default_value = "2"
+    )]
+    pub min_components: usize,
+
+    #[arg(
+        long = "coverage",
+        help = "Also write exonic depth as a bedGraph to this path",
+        value_name = "PATH"
+    )]
+    pub coverage: Option<PathBuf>,
+
+    #[arg(
+        long = "stranded",
+        help = "Split --coverage into <stem>.plus and <stem>.minus bedGraphs",
+        value_name = "FLAG",
+        default_value = "false",
+        requires = "coverage"
+    )]
+    pub stranded: bool,
+
+    #[arg(
+        long = "membership",
+        help = "Also write a transcript/chrom/component index TSV to this path",
+        value_name = "PATH"
+    )]
+    pub membership: Option<PathBuf>,
+
+    #[arg(
+        long = "component_table",
+        help = "Also write a per-component TSV (size, majority strand, strand purity, expression) to this path",
+        value_name = "PATH"
+    )]
+    pub component_table: Option<PathBuf>,
+
+    #[arg(
+        long = "stats",
+        help = "Also write a per-component TSV (transcripts, span, merged exonic bases, strands) to this path",
+        value_name = "PATH"
+    )]
+    pub stats: Option<PathBuf>,
+
+    #[arg(
+        long = "isoforms",
+        help = "Also write a per-component intron chain diversity TSV to this path",
+        value_name = "PATH"
+    )]
+    pub isoforms: Option<PathBuf>,
+
+    #[arg(
+        long = "exon_catalog",
+        help = "Also write the merged exons of every component as BED6 to this path",
+        value_name = "PATH"
+    )]
+    pub exon_catalog: Option<PathBuf>,
+
+    #[arg(
+        long = "wobble",
+        help = "Also write near-matching exon pairs and their boundary offsets to this TSV",
+        value_name = "PATH"
+    )]
+    pub wobble: Option<PathBuf>,
+
+    #[arg(
+        long = "min_overlap_frac",
+        help = "Minimum overlap over the longer exon for --wobble",
+        value_name = "FRAC",
+        default_value = "0.9"
+    )]
+    pub min_overlap_frac: f64,
+
+    #[arg(
+        long = "top",
+        help = "Also write the --top_n largest components to this BED, one comment line per component",
+        value_name = "PATH"
+    )]
+    pub top: Option<PathBuf>,
+
+    #[arg(
+        long = "top_n",
+        help = "Number of components written by --top",
+        value_name = "N",
+        default_value = "100"
+    )]
+    pub top_n: usize,
+
+    #[arg(
+        long = "strip_chrom_prefix",
+        help = "Strip this prefix from chromosome names so e.g. chr1 and 1 unify",
+        value_name = "PREFIX",
+        conflicts_with = "add_chrom_prefix"
+    )]
+    pub strip_chrom_prefix: Option<String>,
+
+    #[arg(
+        long = "add_chrom_prefix",
+        help = "Add this prefix to chromosome names lacking it so e.g. chr1 and 1 unify",
+        value_name = "PREFIX",
+        conflicts_with = "strip_chrom_prefix"
+    )]
+    pub add_chrom_prefix: Option<String>,
 }
 
 #[derive(ValueEnum, Debug, Clone)]
@@ -90,6 +545,93 @@ enum TypeChoice {
     Bin,
     Comp,
     Bed,
+    Tar,
+    Detail,
+    Gff,
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+enum InputChoice {
+    Bed,
+    Genepred,
+    Bed6,
+}
+
+impl From<InputChoice> for InputFormat {
+    fn from(choice: InputChoice) -> Self {
+        match choice {
+            InputChoice::Bed => InputFormat::Bed12,
+            InputChoice::Genepred => InputFormat::GenePred,
+            InputChoice::Bed6 => InputFormat::Bed6,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+enum ModeChoice {
+    Boundary,
+    Exon,
+    Cds,
+}
+
+impl From<ModeChoice> for OverlapMode {
+    fn from(choice: ModeChoice) -> Self {
+        match choice {
+            ModeChoice::Boundary => OverlapMode::Boundary,
+            ModeChoice::Exon => OverlapMode::Exon,
+            ModeChoice::Cds => OverlapMode::Cds,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+enum OrderChoice {
+    Coordinate,
+    Input,
+}
+
+impl From<OrderChoice> for OrderMode {
+    fn from(choice: OrderChoice) -> Self {
+        match choice {
+            OrderChoice::Coordinate => OrderMode::Coordinate,
+            OrderChoice::Input => OrderMode::Input,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+enum ColorChoice {
+    Random,
+    Cyclic,
+    Neighbor,
+}
+
+impl From<ColorChoice> for ColorMode {
+    fn from(choice: ColorChoice) -> Self {
+        match choice {
+            ColorChoice::Random => ColorMode::Random,
+            ColorChoice::Cyclic => ColorMode::Cyclic,
+            ColorChoice::Neighbor => ColorMode::NeighborAware,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+enum PolicyChoice {
+    Keep,
+    Drop,
+    Suffix,
+}
+
+impl From<PolicyChoice> for DuplicateNamePolicy {
+    fn from(choice: PolicyChoice) -> Self {
+        match choice {
+            PolicyChoice::Keep => DuplicateNamePolicy::Keep,
+            PolicyChoice::Drop => DuplicateNamePolicy::Drop,
+            PolicyChoice::Suffix => DuplicateNamePolicy::Suffix,
+        }
+    }
 }
 
 impl Args {
@@ -100,13 +642,38 @@ impl Args {
     fn validate_args(&self) -> anyhow::Result<()> {
         self.check_dbs()?;
 
-        match self.out_type {
-            TypeChoice::Bed => {
-                if !self.colorize {
-                    anyhow::bail!("ERROR: --colorize flag must be set for bed output");
-                }
+        if self.list_chroms || self.fix {
+            return Ok(());
+        }
+
+        if let TypeChoice::Bed = self.out_type {
+            if !self.colorize {
+                anyhow::bail!("ERROR: --colorize flag must be set for bed output");
             }
-            _ => (),
+        } else if self.spill_threshold.is_some() {
+            anyhow::bail!("ERROR: --spill_threshold is only supported for bed output");
+        } else if self.one_based_output {
+            anyhow::bail!("ERROR: --one-based-output is only supported for bed output");
+        }
+
+        if self.output.as_ref().is_some_and(|x| x.as_os_str() == "-")
+            && (!matches!(self.out_type, TypeChoice::Bed)
+                || self.spill_threshold.is_some()
+                || self.fix)
+        {
+            anyhow::bail!("ERROR: -o - (stdout) is only supported for bed output");
+        }
+
+        if self.max_files.is_some() && !matches!(self.out_type, TypeChoice::Comp) {
+            anyhow::bail!("ERROR: --max_files is only supported for comp output");
+        }
+
+        if self.one_based_output && self.spill_threshold.is_some() {
+            anyhow::bail!("ERROR: --one-based-output cannot be combined with --spill_threshold");
+        }
+
+        if let Some(palette) = &self.palette {
+            parse_palette(palette).map_err(|e| anyhow::anyhow!("ERROR: --palette: {}", e))?;
         }
 
         Ok(())
@@ -117,14 +684,21 @@ impl Args {
             let err = "No reference files provided".to_string();
             return Err(anyhow::anyhow!(err));
         }
+        // genePred tables come with all sorts of extensions
+        let any_extension = matches!(self.input_format, InputChoice::Genepred);
         for db in &self.bed {
-            validate(db)?;
+            validate(db, any_extension)?;
         }
         Ok(())
     }
 }
 
-pub fn validate(arg: &PathBuf) -> anyhow::Result<()> {
+pub fn validate(arg: &PathBuf, any_extension: bool) -> anyhow::Result<()> {
+    // stdin
+    if arg.as_os_str() == "-" {
+        return Ok(());
+    }
+
     if !arg.exists() {
         return Err(anyhow::anyhow!("file {:?} does not exist", arg));
     }
@@ -134,48 +708,304 @@ pub fn validate(arg: &PathBuf) -> anyhow::Result<()> {
     }
 
     match arg.extension() {
-        Some(ext) if ext == "bed" || ext == "gz" => (),
+        Some(ext) if ext == "bed" || ext == "gz" || is_annotation(arg) => (),
+        Some(_) if any_extension => (),
         _ => {
             return Err(anyhow::anyhow!("file {:?} is not a BED file", arg));
         }
     }
 
     match std::fs::metadata(arg) {
-        Ok(metadata) if metadata.len() == 0 => {
-            return Err(anyhow::anyhow!("file {:?} is empty", arg));
-        }
+        Ok(metadata) if metadata.len() == 0 => Err(anyhow::anyhow!("file {:?} is empty", arg)),
         Ok(_) => Ok(()),
         Err(e) => Err(e.into()),
     }
 }
 
+fn init_logger(quiet: bool, verbose: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (_, true) => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Info,
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format_timestamp(None)
+        .init();
+}
+
+// packbed and the writers run on the global rayon pool, so --threads has
+// to size that one instead of a pool nobody installs
+fn init_pool(threads: usize) -> Result<(), rayon::ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+}
+
 fn main() {
     let st = std::time::Instant::now();
 
     let args = Args::parse();
+    init_logger(args.quiet, args.verbose);
+
     args.check().unwrap_or_else(|e| {
-        eprintln!("{}", e);
+        log::error!("{}", e);
         std::process::exit(1);
     });
 
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(args.threads)
-        .build()
-        .unwrap();
+    // only fails if something already sized the global pool, in which case
+    // that pool is still usable
+    if let Err(e) = init_pool(args.threads) {
+        log::warn!("Cannot apply --threads {}: {}", args.threads, e);
+    }
+
+    if args.list_chroms {
+        for (chr, count) in chrom_summary(args.bed).expect("ERROR: Failed reading BED files") {
+            println!("{}\t{}", chr, count);
+        }
+        return;
+    }
+
+    let output = args.output.expect("ERROR: --output is required");
 
-    let buckets = packbed(args.bed, args.overlap_cds, args.overlap_exon, args.colorize)
-        .expect("Error packing BED files");
+    if args.fix {
+        let mut report = output.clone().into_os_string();
+        report.push(".fixes.tsv");
+
+        let changed = fix_bed(args.bed, &output, &PathBuf::from(report))
+            .expect("ERROR: Failed writing fixed BED file");
+        log::info!("fixed {} lines", changed);
+        return;
+    }
+    let chrom_normalize = match (args.strip_chrom_prefix, args.add_chrom_prefix) {
+        (Some(prefix), _) => Some(ChromNormalize::Strip(prefix)),
+        (_, Some(prefix)) => Some(ChromNormalize::Add(prefix)),
+        _ => None,
+    };
+
+    let config = PackConfig {
+        colorize_multi_only: args.colorize_multi_only,
+        scale_score: args.scale_score,
+        strict: args.strict,
+        one_based_input: args.one_based_input,
+        chrom: args.chrom,
+        coding_components_only: args.coding_components_only,
+        dedup: args.dedup,
+        min_component_size: args.min_size,
+        chunk_size: args.chunk_size,
+        max_line_len: args.max_line_len,
+        warn_component_count: args.warn_component_count,
+        same_strand_only: args.same_strand_only,
+        min_overlap: args.min_overlap,
+        overlap_type: args.overlap_type,
+        color_mode: args.color_mode.into(),
+        color_seed: args.color_seed,
+        palette: args
+            .palette
+            .as_deref()
+            .map(|x| parse_palette(x).expect("ERROR: palette validated in check")),
+        duplicate_names: args.duplicate_names.map(|policy| DuplicateNames {
+            policy: policy.into(),
+            max_distance: args.duplicate_distance,
+        }),
+        max_intron_len: args.max_intron_len,
+        chrom_normalize,
+        input_format: args.input_format.into(),
+        order_mode: args.order.into(),
+        ..PackConfig::with_mode(args.overlap_mode.into(), args.colorize)
+    };
+
+    if let Some(report) = args.duplicate_report {
+        let policy = config
+            .duplicate_names
+            .as_ref()
+            .map_or(DuplicateNamePolicy::Keep, |x| x.policy);
+        let duplicates = duplicate_name_report(args.bed.clone(), &config, args.duplicate_distance)
+            .expect("ERROR: Failed detecting duplicated names");
+        duplicatewriter(report, &duplicates, policy)
+            .expect("ERROR: Failed writing duplicated names report");
+    }
+
+    if let Some(threshold) = args.spill_threshold {
+        let spilled = packbed_spill(args.bed, &config, threshold).expect("Error packing BED files");
+        spillwriter(&output, &spilled).expect("ERROR: Failed writing components to BED files");
+
+        log::info!("elapsed: {:?}", st.elapsed());
+        return;
+    }
+
+    let buckets = match args.timing {
+        Some(timing) => {
+            let (buckets, report) =
+                packbed_timed(args.bed, &config).expect("Error packing BED files");
+            timingwriter(timing, &report).expect("ERROR: Failed writing timing TSV");
+            buckets
+        }
+        None if args.streaming => {
+            packbed_streaming(args.bed, &config).expect("Error packing BED files")
+        }
+        None => packbed_with(args.bed, &config).expect("Error packing BED files"),
+    };
+
+    let buckets = if args.tag_component {
+        tag_components(buckets)
+    } else {
+        buckets
+    };
+
+    let buckets = if args.collapse {
+        collapse_components(buckets).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            std::process::exit(1);
+        })
+    } else {
+        buckets
+    };
+
+    if let (Some(introns), Some(fasta)) = (args.introns, args.fasta) {
+        intronwriter(introns, &buckets, fasta).expect("ERROR: Failed writing introns");
+    }
+
+    if let Some(hotspots) = args.hotspots {
+        hotspotwriter(hotspots, &buckets, args.min_components)
+            .expect("ERROR: Failed writing hotspots");
+    }
+
+    if let Some(coverage) = args.coverage {
+        coverage_bedgraph(coverage, &buckets, args.stranded)
+            .expect("ERROR: Failed writing coverage bedGraph");
+    }
+
+    if let Some(membership) = args.membership {
+        std::fs::write(membership, membership_table(&buckets))
+            .expect("ERROR: Failed writing membership table");
+    }
+
+    if let Some(stats) = args.stats {
+        statswriter(stats, &component_stats(&buckets))
+            .expect("ERROR: Failed writing component stats");
+    }
+
+    if let Some(isoforms) = args.isoforms {
+        isoformwriter(isoforms, &buckets).expect("ERROR: Failed writing isoform diversity TSV");
+    }
+
+    if let Some(wobble) = args.wobble {
+        wobblewriter(wobble, &buckets, args.min_overlap_frac)
+            .expect("ERROR: Failed writing boundary wobble TSV");
+    }
+
+    if let Some(catalog) = args.exon_catalog {
+        exoncatalogwriter(catalog, &buckets).expect("ERROR: Failed writing exon catalog");
+    }
+
+    if let Some(top) = args.top {
+        topwriter(top, &buckets, args.top_n).expect("ERROR: Failed writing largest components");
+    }
+
+    if let Some(table) = args.component_table {
+        std::fs::write(table, component_table(&buckets))
+            .expect("ERROR: Failed writing component table");
+    }
+
+    if let (Some(novel), Some(reference)) = (args.novel_introns, args.reference) {
+        let flatten = |buckets: &DashMap<String, Vec<Vec<GenePred>>>| {
+            buckets
+                .iter()
+                .map(|x| (x.key().clone(), x.value().concat()))
+                .collect::<GenePredMap>()
+        };
+        // parsed like the inputs so chromosome names and blocks line up, but
+        // with every reference record kept as is
+        let reference_config = PackConfig {
+            colorize: false,
+            dedup: false,
+            scale_score: false,
+            duplicate_names: None,
+            coding_components_only: false,
+            min_component_size: 0,
+            ..config.clone()
+        };
+        let reference = packbed_with(vec![reference], &reference_config)
+            .expect("ERROR: Failed reading reference annotation");
+
+        novelintronwriter(
+            novel,
+            &novel_introns(&flatten(&buckets), &flatten(&reference)),
+        )
+        .expect("ERROR: Failed writing novel introns");
+    }
+
+    if let Some(regions) = args.regions {
+        let regions = read_regions(regions).expect("ERROR: Failed reading regions BED");
+        partition_by_regions(&buckets, &regions, &output)
+            .expect("ERROR: Failed writing components by region");
+
+        log::info!("elapsed: {:?}", st.elapsed());
+        return;
+    }
+
+    if let Some(old) = args.diff {
+        let diff = diff_components(old, &buckets).expect("ERROR: Failed diffing components");
+        diffwriter(&diff, &output).expect("ERROR: Failed writing component diff");
+
+        log::info!("elapsed: {:?}", st.elapsed());
+        return;
+    }
 
     match args.out_type {
         TypeChoice::Bin => {
-            binwriter(&args.output, buckets).expect("ERROR: Failed writing binary of components");
+            binwriter(&output, buckets).expect("ERROR: Failed writing binary of components");
+        }
+        TypeChoice::Comp => compwriter_with(
+            buckets,
+            &output,
+            args.subdirs,
+            args.max_files,
+            args.sorted,
+            args.compress,
+        )
+        .expect("ERROR: Failed writing components to BED files"),
+        TypeChoice::Bed if output.as_os_str() == "-" => {
+            let mut stdout = BufWriter::new(std::io::stdout().lock());
+            bedwriter_to(&mut stdout, buckets, args.one_based_output, args.sorted)
+                .and_then(|_| Ok(stdout.flush()?))
+                .expect("ERROR: Failed writing components to stdout")
         }
-        TypeChoice::Comp => compwriter(buckets, &args.output, args.subdirs)
+        TypeChoice::Bed => bedwriter_with(&output, buckets, args.one_based_output, args.sorted)
             .expect("ERROR: Failed writing components to BED files"),
-        TypeChoice::Bed => {
-            bedwriter(&args.output, buckets).expect("ERROR: Failed writing components to BED files")
+        TypeChoice::Detail => detailwriter(&output, buckets, "packbed")
+            .expect("ERROR: Failed writing BED detail and autoSql schema"),
+        TypeChoice::Gff => {
+            gff3writer(&output, &buckets).expect("ERROR: Failed writing components to GFF3")
+        }
+        TypeChoice::Json => {
+            jsonwriter(&output, buckets).expect("ERROR: Failed writing components to JSON")
+        }
+        TypeChoice::Tar => {
+            tarwriter(&output, buckets).expect("ERROR: Failed writing components to archive")
         }
     }
 
-    dbg!(st.elapsed());
+    log::info!("elapsed: {:?}", st.elapsed());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_init_pool_sets_global_threads() {
+        init_pool(3).unwrap();
+        assert_eq!(rayon::current_num_threads(), 3);
+
+        // packbed spreads chromosomes over the same pool
+        let threads = (0..64)
+            .into_par_iter()
+            .map(|_| rayon::current_thread_index().unwrap())
+            .collect::<hashbrown::HashSet<_>>();
+        assert!(threads.iter().all(|&i| i < 3));
+    }
 }
@@ -0,0 +1,203 @@
+use std::fmt::{self, Debug, Display};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use hashbrown::HashMap;
+
+use crate::{unpack, GenePredMap, PackConfig};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum DuplicateNamePolicy {
+    #[default]
+    Keep,
+    Drop,
+    Suffix,
+}
+
+impl Display for DuplicateNamePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DuplicateNamePolicy::Keep => write!(f, "kept"),
+            DuplicateNamePolicy::Drop => write!(f, "dropped"),
+            DuplicateNamePolicy::Suffix => write!(f, "suffixed"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct DuplicateNames {
+    pub policy: DuplicateNamePolicy,
+    // records sharing a name further apart than this are distinct loci
+    pub max_distance: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DuplicateName {
+    pub name: String,
+    // (chrom, genomic start, genomic end) per locus, sorted
+    pub loci: Vec<(String, u64, u64)>,
+}
+
+// names whose records fall on more than one chromosome or on loci more
+// than max_distance apart
+pub fn find_duplicate_names(tracks: &GenePredMap, max_distance: u64) -> Vec<DuplicateName> {
+    let mut spans: HashMap<&str, Vec<(&str, u64, u64)>> = HashMap::new();
+    for (chr, transcripts) in tracks {
+        for tx in transcripts {
+            let (start, end) = (tx.start, tx.end);
+            spans.entry(&tx.name).or_default().push((chr, start, end));
+        }
+    }
+
+    let mut duplicates = spans
+        .into_iter()
+        .filter(|(_, x)| x.len() > 1)
+        .filter_map(|(name, mut x)| {
+            x.sort_unstable();
+
+            let mut loci: Vec<(String, u64, u64)> = Vec::new();
+            for (chr, start, end) in x {
+                match loci.last_mut() {
+                    Some(last) if last.0 == chr && start <= last.2 + max_distance => {
+                        last.2 = last.2.max(end)
+                    }
+                    _ => loci.push((chr.to_string(), start, end)),
+                }
+            }
+
+            (loci.len() > 1).then(|| DuplicateName {
+                name: name.to_string(),
+                loci,
+            })
+        })
+        .collect::<Vec<_>>();
+    duplicates.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+    duplicates
+}
+
+// drops or renames ({name}_{k}, k being the locus rank) flagged records
+pub fn resolve_duplicate_names(
+    tracks: &mut GenePredMap,
+    duplicates: &[DuplicateName],
+    policy: DuplicateNamePolicy,
+) {
+    if policy == DuplicateNamePolicy::Keep || duplicates.is_empty() {
+        return;
+    }
+
+    let flagged = duplicates
+        .iter()
+        .map(|x| (x.name.as_str(), x))
+        .collect::<HashMap<_, _>>();
+
+    for (chr, transcripts) in tracks.iter_mut() {
+        *transcripts = std::mem::take(transcripts)
+            .into_iter()
+            .filter_map(|tx| match flagged.get(tx.name.as_str()) {
+                None => Some(tx),
+                Some(_) if policy == DuplicateNamePolicy::Drop => None,
+                Some(dup) => {
+                    let (start, end) = (tx.start, tx.end);
+                    let k = dup
+                        .loci
+                        .iter()
+                        .position(|(c, s, e)| c == chr && *s <= start && end <= *e)
+                        .unwrap_or_default();
+                    let name = format!("{}_{}", tx.name, k);
+                    Some(tx.rename(name))
+                }
+            })
+            .collect();
+    }
+    tracks.retain(|_, x| !x.is_empty());
+}
+
+// name, chrom, start, end and the action taken, one row per locus
+pub fn duplicatewriter<P: AsRef<Path> + Debug>(
+    file: P,
+    duplicates: &[DuplicateName],
+    policy: DuplicateNamePolicy,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+    writeln!(file, "name\tchrom\tstart\tend\taction")?;
+
+    for dup in duplicates {
+        for (chr, start, end) in &dup.loci {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}\t{}",
+                dup.name, chr, start, end, policy
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// detection alone over the inputs, for reporting before packing
+pub fn duplicate_name_report<P: AsRef<Path> + Debug + Sync + Send>(
+    files: Vec<P>,
+    config: &PackConfig,
+    max_distance: u64,
+) -> Result<Vec<DuplicateName>, anyhow::Error> {
+    let config = PackConfig {
+        duplicate_names: None,
+        ..config.clone()
+    };
+    let tracks = unpack(files, &config)?;
+
+    Ok(find_duplicate_names(&tracks, max_distance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packbed_with;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_duplicate_name_two_chroms() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns9\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns9\t500\t600\tread2\t0\t+\t510\t590\t0\t1\t100,\t0,\ns9\t520\t620\tread2\t0\t+\t530\t610\t0\t1\t90,\t0,"
+        )
+        .unwrap();
+
+        let config = PackConfig {
+            duplicate_names: Some(DuplicateNames {
+                policy: DuplicateNamePolicy::Suffix,
+                max_distance: 1000,
+            }),
+            ..PackConfig::new(false, false, false)
+        };
+
+        let duplicates = duplicate_name_report(vec![file.path()], &config, 1000).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].name, "read1");
+        assert_eq!(
+            duplicates[0].loci,
+            vec![("s8".to_string(), 100, 200), ("s9".to_string(), 100, 200)]
+        );
+
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        assert_eq!(buckets.get("s8").unwrap()[0][0].name, "read1_0");
+        assert_eq!(buckets.get("s9").unwrap()[0][0].name, "read1_1");
+        assert!(buckets.get("s9").unwrap()[0][0]
+            .line()
+            .contains("\tread1_1\t"));
+
+        let config = PackConfig {
+            duplicate_names: Some(DuplicateNames {
+                policy: DuplicateNamePolicy::Drop,
+                max_distance: 1000,
+            }),
+            ..PackConfig::new(false, false, false)
+        };
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        assert!(buckets.get("s8").is_none());
+        assert_eq!(buckets.get("s9").unwrap()[0][0].name, "read2");
+    }
+}
@@ -0,0 +1,364 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use hashbrown::HashMap;
+use rayon::prelude::*;
+
+use crate::{intervals, GenePred, GenePredMap};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OverlapPair {
+    pub chrom: String,
+    pub query: String,
+    pub target: String,
+    pub overlap: u64,
+    pub query_len: u64,
+    pub target_len: u64,
+}
+
+// which intervals of a transcript take part in the overlap. Cds compares
+// exons like Exon, but only works on records parsed with cds_overlap
+// (Bed12::parse_with), which clips them to the CDS; packing with a Cds
+// PackConfig does that for you
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum OverlapMode {
+    // txStart..txEnd as a single interval
+    #[default]
+    Boundary,
+    Exon,
+    Cds,
+}
+
+impl OverlapMode {
+    // the overlap_cds/overlap_exon flag pair; cds wins if both are set
+    pub fn from_flags(overlap_cds: bool, overlap_exon: bool) -> Self {
+        match (overlap_cds, overlap_exon) {
+            (true, _) => OverlapMode::Cds,
+            (false, true) => OverlapMode::Exon,
+            (false, false) => OverlapMode::Boundary,
+        }
+    }
+}
+
+impl FromStr for OverlapMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "boundary" => Ok(OverlapMode::Boundary),
+            "exon" => Ok(OverlapMode::Exon),
+            "cds" => Ok(OverlapMode::Cds),
+            _ => Err(format!(
+                "unknown overlap mode {}, expected boundary, exon or cds",
+                s
+            )),
+        }
+    }
+}
+
+// how the sweep decides two transcripts belong together, see
+// PackConfig::overlap_type
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum OverlapType {
+    // any shared base
+    #[default]
+    Any,
+    // shared bases cover at least this fraction of both transcripts' lengths
+    Reciprocal(f64),
+    // at least one identical intron, i.e. a shared splice junction pair
+    Intron,
+}
+
+impl FromStr for OverlapType {
+    type Err = String;
+
+    // any | intron | reciprocal:<f> with 0 < f <= 1
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "any" => Ok(OverlapType::Any),
+            None if s == "intron" => Ok(OverlapType::Intron),
+            Some(("reciprocal", f)) => match f.parse::<f64>() {
+                Ok(f) if f > 0.0 && f <= 1.0 => Ok(OverlapType::Reciprocal(f)),
+                _ => Err(format!("reciprocal fraction must be in (0, 1], got {}", f)),
+            },
+            _ => Err(format!(
+                "unknown overlap type {}, expected any, intron or reciprocal:<f>",
+                s
+            )),
+        }
+    }
+}
+
+// summed shared length of every overlapping pair (lower index first) and
+// the total interval length of each transcript; strands are swept apart
+// like in the default mode, so opposite-strand pairs never show up
+pub(crate) fn shared_bases(
+    transcripts: &[GenePred],
+    mode: OverlapMode,
+) -> (HashMap<(usize, usize), u64>, Vec<u64>) {
+    let (mut plus, mut minus) = (Vec::new(), Vec::new());
+    let mut lengths = vec![0; transcripts.len()];
+
+    for (i, transcript) in transcripts.iter().enumerate() {
+        let ranges = if transcript.strand == '-' {
+            &mut minus
+        } else {
+            &mut plus
+        };
+        for (start, end) in intervals(transcript, mode) {
+            lengths[i] += end - start;
+            ranges.push((start, end, i));
+        }
+    }
+
+    let mut shared: HashMap<(usize, usize), u64> = HashMap::new();
+    for mut ranges in [plus, minus] {
+        ranges.sort_unstable_by_key(|a| a.0);
+
+        // sweep keeping every interval still open at the current start,
+        // unlike buckerize that only keeps the running max
+        let mut active: Vec<(u64, usize)> = Vec::new();
+        for &(start, end, idx) in &ranges {
+            active.retain(|&(active_end, _)| active_end > start);
+
+            for &(active_end, active_idx) in &active {
+                if active_idx != idx {
+                    let key = (active_idx.min(idx), active_idx.max(idx));
+                    *shared.entry(key).or_default() += active_end.min(end) - start;
+                }
+            }
+
+            active.push((end, idx));
+        }
+    }
+
+    (shared, lengths)
+}
+
+// raw overlap graph behind the components: every pair of transcripts sharing
+// at least one base, with the summed length of their overlapping intervals
+pub fn overlap_pairs(
+    tracks: &GenePredMap,
+    overlap_cds: bool,
+    overlap_exon: bool,
+) -> Vec<OverlapPair> {
+    let mut pairs = tracks
+        .par_iter()
+        .flat_map(|(chr, transcripts)| {
+            let (shared, lengths) = shared_bases(
+                transcripts,
+                OverlapMode::from_flags(overlap_cds, overlap_exon),
+            );
+
+            let mut shared = shared.into_iter().collect::<Vec<_>>();
+            shared.sort_unstable();
+
+            shared
+                .into_iter()
+                .map(|((q, t), overlap)| OverlapPair {
+                    chrom: chr.clone(),
+                    query: transcripts[q].name.clone(),
+                    target: transcripts[t].name.clone(),
+                    overlap,
+                    query_len: lengths[q],
+                    target_len: lengths[t],
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    pairs.par_sort_by(|a, b| a.chrom.cmp(&b.chrom));
+
+    pairs
+}
+
+pub fn pairwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    pairs: &[OverlapPair],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    for pair in pairs {
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            pair.chrom, pair.query, pair.target, pair.overlap, pair.query_len, pair.target_len
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OverlapClass {
+    // share exonic sequence
+    ExonExon,
+    // an exon of one covers an intron of the other (intron retention), or
+    // sits in it without any intron of its own overlapping
+    ExonIntron,
+    // no shared exonic sequence but introns overlap, e.g. a spliced
+    // transcript nested inside another's intron
+    IntronIntron,
+    Disjoint,
+}
+
+impl std::fmt::Display for OverlapClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let class = match self {
+            OverlapClass::ExonExon => "exon-exon",
+            OverlapClass::ExonIntron => "exon-intron",
+            OverlapClass::IntronIntron => "intron-intron",
+            OverlapClass::Disjoint => "disjoint",
+        };
+        write!(f, "{}", class)
+    }
+}
+
+#[inline(always)]
+fn any_covers(a: &[(u64, u64)], b: &[(u64, u64)]) -> bool {
+    a.iter()
+        .any(|&(sa, ea)| b.iter().any(|&(sb, eb)| sa <= sb && eb <= ea))
+}
+
+#[inline(always)]
+fn any_overlap(a: &[(u64, u64)], b: &[(u64, u64)]) -> bool {
+    a.iter()
+        .any(|&(sa, ea)| b.iter().any(|&(sb, eb)| sa < eb && sb < ea))
+}
+
+// compares in genomic coordinates so records on opposite strands classify
+pub fn classify_overlap(a: &GenePred, b: &GenePred) -> OverlapClass {
+    let (exons_a, exons_b) = (&a.exons, &b.exons);
+    let (introns_a, introns_b) = (&a.introns, &b.introns);
+
+    // retention takes precedence, a retained intron always shares exonic sequence
+    if any_covers(exons_a, introns_b) || any_covers(exons_b, introns_a) {
+        return OverlapClass::ExonIntron;
+    }
+
+    if any_overlap(exons_a, exons_b) {
+        return OverlapClass::ExonExon;
+    }
+
+    if any_overlap(introns_a, introns_b) {
+        return OverlapClass::IntronIntron;
+    }
+
+    if any_overlap(exons_a, introns_b) || any_overlap(introns_a, exons_b) {
+        return OverlapClass::ExonIntron;
+    }
+
+    OverlapClass::Disjoint
+}
+
+// every pair of transcripts with overlapping genomic spans and its class
+pub fn classified_pairs(tracks: &GenePredMap) -> Vec<(String, String, String, OverlapClass)> {
+    let mut pairs = tracks
+        .par_iter()
+        .flat_map(|(chr, transcripts)| {
+            let mut spans = transcripts
+                .iter()
+                .enumerate()
+                .map(|(i, x)| ((x.start, x.end), i))
+                .collect::<Vec<_>>();
+            spans.sort_unstable();
+
+            let mut pairs = Vec::new();
+            for (k, &((_, end), i)) in spans.iter().enumerate() {
+                for &((start, _), j) in &spans[k + 1..] {
+                    if start >= end {
+                        break;
+                    }
+
+                    let (a, b) = (&transcripts[i], &transcripts[j]);
+                    pairs.push((
+                        chr.clone(),
+                        a.name.clone(),
+                        b.name.clone(),
+                        classify_overlap(a, b),
+                    ));
+                }
+            }
+
+            pairs
+        })
+        .collect::<Vec<_>>();
+
+    pairs.par_sort_by(|a, b| a.0.cmp(&b.0));
+
+    pairs
+}
+
+pub fn classwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    pairs: &[(String, String, String, OverlapClass)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    for (chr, a, b, class) in pairs {
+        writeln!(file, "{}\t{}\t{}\t{}", chr, a, b, class)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bed12;
+
+    fn tracks(lines: &[&str]) -> GenePredMap {
+        let mut tracks = GenePredMap::new();
+        for line in lines {
+            let record = Bed12::parse(line, false).unwrap();
+            tracks.entry(record.chrom.clone()).or_default().push(record);
+        }
+        tracks
+    }
+
+    #[test]
+    fn test_overlap_pairs_known_overlap() {
+        let tracks = tracks(&[
+            "s8\t100\t200\tread1\t0\t+\t100\t200\t0\t2\t20,30,\t0,70,",
+            "s8\t150\t300\tread2\t0\t+\t150\t300\t0\t2\t60,50,\t0,100,",
+            "s8\t500\t600\tread3\t0\t+\t500\t600\t0\t1\t100,\t0,",
+        ]);
+
+        let pairs = overlap_pairs(&tracks, false, true);
+
+        // read1 exon (170,200) vs read2 exon (150,210)
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].query, "read1");
+        assert_eq!(pairs[0].target, "read2");
+        assert_eq!(pairs[0].overlap, 30);
+        assert_eq!(pairs[0].query_len, 50);
+        assert_eq!(pairs[0].target_len, 110);
+
+        let pairs = overlap_pairs(&tracks, false, false);
+        assert_eq!(pairs[0].overlap, 50);
+    }
+
+    #[test]
+    fn test_classify_overlap_each_class() {
+        let parse = |line: &str| Bed12::parse(line, false).unwrap();
+        // exons (100,150) (250,300)
+        let a = parse("s8\t100\t300\ta\t0\t+\t100\t300\t0\t2\t50,50,\t0,150,");
+
+        let exon_exon = parse("s8\t140\t200\tb\t0\t-\t140\t200\t0\t1\t60,\t0,");
+        let exon_intron = parse("s8\t170\t230\tc\t0\t+\t170\t230\t0\t1\t60,\t0,");
+        let intron_intron = parse("s8\t50\t400\td\t0\t+\t50\t400\t0\t2\t40,50,\t0,300,");
+        let disjoint = parse("s8\t500\t600\te\t0\t+\t500\t600\t0\t1\t100,\t0,");
+
+        assert_eq!(classify_overlap(&a, &exon_exon), OverlapClass::ExonExon);
+        assert_eq!(classify_overlap(&a, &exon_intron), OverlapClass::ExonIntron);
+        assert_eq!(classify_overlap(&exon_intron, &a), OverlapClass::ExonIntron);
+        assert_eq!(
+            classify_overlap(&a, &intron_intron),
+            OverlapClass::IntronIntron
+        );
+        assert_eq!(classify_overlap(&a, &disjoint), OverlapClass::Disjoint);
+    }
+}
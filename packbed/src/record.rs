@@ -1,11 +1,80 @@
-use hashbrown::HashSet;
-use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
 
-const SCALE: u64 = 100000000000; // 100Gb
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Bed12;
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct Gtf;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GenePredParser;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bed6;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ParseError {
+    EmptyLine,
+    // the named column is not there
+    MissingField(&'static str),
+    BadStrand(char),
+    // the named column is not an unsigned integer
+    NumberParse(&'static str),
+    UtrInCds,
+    // exon starts and sizes (or ends) differ in count
+    LengthMismatch,
+    DuplicatedBlocks,
+    // strict only: a block leaves [tx_start, tx_end) or the blocks do not
+    // start at tx_start and end at tx_end
+    BlockOutOfBounds,
+    // the named coordinate overflows u64 once blocks are added
+    OutOfRange(&'static str),
+    // GTF/GFF3 and genePred specific problems
+    Invalid(&'static str),
+    // the source failed while reading, see Bed12Reader
+    Io(std::io::ErrorKind),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::EmptyLine => write!(f, "Empty line"),
+            ParseError::MissingField(field) => write!(f, "Cannot parse {}", field),
+            ParseError::BadStrand(strand) => write!(f, "Strand {} is not + or -", strand),
+            ParseError::NumberParse(field) => write!(f, "Cannot parse {} as a number", field),
+            ParseError::UtrInCds => write!(f, "UTRs are not allowed in CDS exons"),
+            ParseError::LengthMismatch => {
+                write!(f, "Exon start and end vectors have different lengths")
+            }
+            ParseError::DuplicatedBlocks => write!(f, "Record lists duplicated exon blocks"),
+            ParseError::BlockOutOfBounds => {
+                write!(f, "Exon blocks do not match the transcript bounds")
+            }
+            ParseError::OutOfRange(field) => write!(f, "{} overflows the coordinate range", field),
+            ParseError::Invalid(msg) => write!(f, "{}", msg),
+            ParseError::Io(kind) => write!(f, "Cannot read input: {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// line format of the input, GTF/GFF3 is picked by extension instead
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum InputFormat {
+    #[default]
+    Bed12,
+    // UCSC genePred/genePredExt tables
+    GenePred,
+    // plain intervals, one non-coding exon each
+    Bed6,
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct GenePred {
     pub name: String,
@@ -16,10 +85,19 @@ pub struct GenePred {
     pub cds_start: u64,
     pub cds_end: u64,
     pub exons: Vec<(u64, u64)>,
+    // half-open [start, end) gaps between exons, the same convention as exons
     pub introns: Vec<(u64, u64)>,
     pub exon_count: usize,
     pub rgb: String,
     pub line: String,
+    // columns past the 12th of BED12+ input, verbatim and empty for plain
+    // BED12; they stay in line too, so writers round-trip them
+    #[serde(default)]
+    pub extra: Vec<String>,
+    // 0-based line in the concatenated inputs, only filled by parse_tracks
+    // under OrderMode::Input
+    #[serde(default)]
+    pub order: usize,
 }
 
 impl GenePred {
@@ -31,34 +109,188 @@ impl GenePred {
         &self.name
     }
 
-    pub fn colorline(self: Self, color: &str) -> Self {
-        let nline = self.line.clone();
-        let mut fields = nline.split('\t').collect::<Vec<_>>();
-        fields[8] = color;
-        let new_line = fields.join("\t");
-
-        GenePred {
-            line: new_line,
-            name: self.name.clone(),
-            chrom: self.chrom.clone(),
-            strand: self.strand,
-            start: self.start,
-            end: self.end,
-            cds_start: self.cds_start,
-            cds_end: self.cds_end,
-            exons: self.exons.clone(),
-            introns: self.introns.clone(),
-            rgb: color.into(),
-            exon_count: self.exon_count,
+    pub fn rename(mut self, name: String) -> Self {
+        let mut fields = self.line.split('\t').collect::<Vec<_>>();
+        if fields.len() > 3 {
+            fields[3] = &name;
+            self.line = fields.join("\t");
+        }
+        self.name = name;
+        self
+    }
+
+    // moves the record to another chromosome name, line included
+    pub fn rename_chrom(mut self, chrom: String) -> Self {
+        if let Some((_, rest)) = self.line.split_once('\t') {
+            self.line = format!("{}\t{}", chrom, rest);
         }
+        self.chrom = chrom;
+        self
+    }
+
+    // rebuilds a BED12 line from the record fields, keeping the
+    // score of the original line
+    pub fn to_bed12(&self) -> String {
+        let (start, end) = (self.start, self.end);
+        let (cds_start, cds_end) = (self.cds_start, self.cds_end);
+        let exons = &self.exons;
+        let score = self.line.split('\t').nth(4).unwrap_or("0");
+
+        let sizes = exons
+            .iter()
+            .map(|(s, e)| format!("{},", e - s))
+            .collect::<String>();
+        let starts = exons
+            .iter()
+            .map(|(s, _)| format!("{},", s - start))
+            .collect::<String>();
+
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.chrom,
+            start,
+            end,
+            self.name,
+            score,
+            self.strand,
+            cds_start,
+            cds_end,
+            self.rgb,
+            exons.len(),
+            sizes,
+            starts
+        )
+    }
+
+    // breaks the record at every intron longer than max_intron_len, each
+    // fragment gets its own exons, introns, clipped cds and line
+    pub fn split_at_introns(self, max_intron_len: u64) -> Vec<GenePred> {
+        // exons may overlap or nest, so gaps are taken from the running max
+        // end of the block rather than from its last exon
+        let mut blocks: Vec<(Vec<(u64, u64)>, u64)> = vec![];
+        for &exon in &self.exons {
+            match blocks.last_mut() {
+                Some((block, end)) if exon.0.saturating_sub(*end) <= max_intron_len => {
+                    block.push(exon);
+                    *end = (*end).max(exon.1);
+                }
+                _ => blocks.push((vec![exon], exon.1)),
+            }
+        }
+
+        if blocks.len() < 2 {
+            return vec![self];
+        }
+
+        blocks
+            .into_iter()
+            .enumerate()
+            .map(|(i, (exons, end))| {
+                let start = exons[0].0;
+                let (mut cds_start, mut cds_end) = (
+                    self.cds_start.clamp(start, end),
+                    self.cds_end.clamp(start, end),
+                );
+                if cds_start >= cds_end {
+                    (cds_start, cds_end) = (start, start);
+                }
+
+                let introns = gapper(&exons.iter().copied().collect());
+                let mut introns = introns.into_iter().collect::<Vec<_>>();
+                introns.sort_unstable();
+
+                let mut fragment = GenePred {
+                    name: format!("{}_{}", self.name, i + 1),
+                    chrom: self.chrom.clone(),
+                    strand: self.strand,
+                    start,
+                    end,
+                    cds_start,
+                    cds_end,
+                    exon_count: exons.len(),
+                    exons,
+                    introns,
+                    rgb: self.rgb.clone(),
+                    line: self.line.clone(),
+                    extra: self.extra.clone(),
+                    order: self.order,
+                };
+                fragment.line = fragment.to_bed12();
+                for field in &fragment.extra {
+                    fragment.line.push('\t');
+                    fragment.line.push_str(field);
+                }
+
+                fragment
+            })
+            .collect()
+    }
+
+    pub fn colorline(mut self, color: &str) -> Self {
+        // only column 9 is rewritten, everything else moves through as is
+        let mut tabs = self.line.match_indices('\t').map(|(i, _)| i);
+        let bounds = tabs
+            .nth(7)
+            .map(|i| (i + 1, tabs.next().unwrap_or(self.line.len())));
+
+        match bounds {
+            Some((start, end)) => self.line.replace_range(start..end, color),
+            None => {
+                // short lines (BED6 and the like) get the missing columns
+                // filled with BED defaults so itemRgb has somewhere to go
+                let fields = self.line.split('\t').collect::<Vec<_>>();
+                let defaults = [
+                    "",
+                    "",
+                    "",
+                    self.name.as_str(),
+                    "0",
+                    ".",
+                    fields.get(1).copied().unwrap_or("0"),
+                    fields.get(2).copied().unwrap_or("0"),
+                ];
+
+                let mut tail = String::new();
+                for default in &defaults[fields.len()..] {
+                    tail.push('\t');
+                    tail.push_str(default);
+                }
+                tail.push('\t');
+                tail.push_str(color);
+
+                self.line.push_str(&tail);
+            }
+        }
+
+        self.rgb = color.into();
+        self
+    }
+
+    pub fn score(&self) -> Option<f64> {
+        self.line.split('\t').nth(4)?.parse::<f64>().ok()
+    }
+
+    pub fn scoreline(mut self, score: u64) -> Self {
+        let mut fields = self.line.split('\t').collect::<Vec<_>>();
+        let score = score.to_string();
+        fields[4] = &score;
+        self.line = fields.join("\t");
+        self
     }
 }
 
 impl Bed12 {
     #[inline(always)]
-    pub fn parse(line: &str, cds_overlap: bool) -> Result<GenePred, &'static str> {
+    pub fn parse(line: &str, cds_overlap: bool) -> Result<GenePred, ParseError> {
+        Self::parse_with(line, cds_overlap, false)
+    }
+
+    // duplicated blocks collapse into one exon either way; strict turns
+    // the warning into an error
+    #[inline(always)]
+    pub fn parse_with(line: &str, cds_overlap: bool, strict: bool) -> Result<GenePred, ParseError> {
         if line.is_empty() {
-            return Err("Empty line");
+            return Err(ParseError::EmptyLine);
         }
 
         let mut fields = line.split('\t');
@@ -76,38 +308,56 @@ impl Bed12 {
             exon_sizes,
             exon_starts,
         ) = (
-            fields.next().ok_or("Cannot parse chrom")?,
-            fields.next().ok_or("Cannot parse tx_start")?,
-            fields.next().ok_or("Cannot parse tx_end")?,
-            fields.next().ok_or("Cannot parse name")?,
-            fields.next().ok_or("Cannot parse score")?,
+            fields.next().ok_or(ParseError::MissingField("chrom"))?,
+            fields.next().ok_or(ParseError::MissingField("tx_start"))?,
+            fields.next().ok_or(ParseError::MissingField("tx_end"))?,
+            fields.next().ok_or(ParseError::MissingField("name"))?,
+            fields.next().ok_or(ParseError::MissingField("score"))?,
             fields
                 .next()
-                .ok_or("Cannot parse strand")?
+                .ok_or(ParseError::MissingField("strand"))?
                 .chars()
                 .next()
-                .ok_or("Cannot parse strand as char")?,
-            fields.next().ok_or("Cannot parse cds_start")?,
-            fields.next().ok_or("Cannot parse cds_end")?,
-            fields.next().ok_or("Cannot parse rgb")?,
-            fields.next().ok_or("Cannot parse block_count")?,
-            fields.next().ok_or("Cannot parse exon_sizes")?,
-            fields.next().ok_or("Cannot parse exon_starts")?,
+                .ok_or(ParseError::MissingField("strand"))?,
+            fields.next().ok_or(ParseError::MissingField("cds_start"))?,
+            fields.next().ok_or(ParseError::MissingField("cds_end"))?,
+            fields.next().ok_or(ParseError::MissingField("rgb"))?,
+            fields
+                .next()
+                .ok_or(ParseError::MissingField("block_count"))?,
+            fields
+                .next()
+                .ok_or(ParseError::MissingField("exon_sizes"))?,
+            fields
+                .next()
+                .ok_or(ParseError::MissingField("exon_starts"))?,
         );
 
         if strand != '+' && strand != '-' {
-            return Err("Strand is not + or -");
+            return Err(ParseError::BadStrand(strand));
         }
 
-        let get = |field: &str| field.parse::<u64>().map_err(|_| "Cannot parse field");
+        let extra = fields.map(String::from).collect::<Vec<_>>();
+
+        if has_duplicated_blocks(exon_starts, exon_sizes) {
+            if strict {
+                return Err(ParseError::DuplicatedBlocks);
+            }
+            log::warn!("{} lists duplicated exon blocks, collapsing them", name);
+        }
+
+        let get = |field: &str, name| parse_u64(field).map_err(|_| ParseError::NumberParse(name));
         let (tx_start, tx_end, cds_start, cds_end) =
             abs_pos(tx_start, tx_end, cds_start, cds_end, strand, get)?;
 
+        if strict {
+            check_block_bounds(exon_starts, exon_sizes, tx_start, tx_end)?;
+        }
+
         let (exons, introns) = get_coords(
             exon_starts,
             exon_sizes,
             tx_start,
-            tx_end,
             cds_start,
             cds_end,
             strand,
@@ -115,10 +365,10 @@ impl Bed12 {
         )?;
 
         let mut exons = exons.iter().cloned().collect::<Vec<_>>();
-        exons.sort_unstable_by(|a, b| a.cmp(b));
+        exons.sort_unstable();
 
         let mut introns = introns.iter().cloned().collect::<Vec<_>>();
-        introns.sort_unstable_by(|a, b| a.cmp(b));
+        introns.sort_unstable();
 
         let exon_count = exons.len();
 
@@ -135,85 +385,453 @@ impl Bed12 {
             exon_count,
             rgb: rgb.into(),
             line: line.to_string(),
+            extra,
+            order: 0,
         })
     }
 }
 
+// lazy line-by-line parse of any BufRead; comment and blank lines are
+// skipped, a bad line yields its error and iteration goes on
+pub struct Bed12Reader<R: BufRead> {
+    source: R,
+    cds_overlap: bool,
+    line: String,
+}
+
+impl<R: BufRead> Bed12Reader<R> {
+    pub fn new(source: R, cds_overlap: bool) -> Self {
+        Self {
+            source,
+            cds_overlap,
+            line: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Bed12Reader<R> {
+    type Item = Result<GenePred, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.source.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(e) => return Some(Err(ParseError::Io(e.kind()))),
+            }
+
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if !line.is_empty() && !line.starts_with('#') {
+                return Some(Bed12::parse(line, self.cds_overlap));
+            }
+        }
+    }
+}
+
+// .gtf, .gff and .gff3 inputs, optionally gzipped
+pub fn is_annotation<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    let path = match path.extension() {
+        Some(ext) if ext == "gz" => Path::new(path.file_stem().unwrap_or_default()),
+        _ => path,
+    };
+
+    matches!(
+        path.extension().and_then(|x| x.to_str()),
+        Some("gtf" | "gff" | "gff3")
+    )
+}
+
+// transcript_id "x"; (GTF) or transcript_id=x / Parent=x (GFF3)
+fn attribute<'a>(attributes: &'a str, key: &str) -> Option<&'a str> {
+    attributes.split(';').find_map(|x| {
+        let (k, v) = x.trim().split_once([' ', '='])?;
+        (k == key).then(|| v.trim().trim_matches('"'))
+    })
+}
+
+#[derive(Default)]
+struct GtfTranscript {
+    chrom: String,
+    strand: char,
+    exons: Vec<(u64, u64)>,
+    cds: Option<(u64, u64)>,
+}
+
+impl Gtf {
+    // groups exon and CDS features by transcript into BED12 lines, in the
+    // order transcripts first appear; start/stop codons extend the CDS
+    pub fn to_bed12(contents: &str) -> Result<String, ParseError> {
+        let mut order = Vec::new();
+        let mut transcripts: HashMap<&str, GtfTranscript> = HashMap::new();
+
+        for line in contents.lines() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() < 9 {
+                return Err(ParseError::Invalid("GTF/GFF line has fewer than 9 columns"));
+            }
+
+            let feature = fields[2];
+            if !matches!(feature, "exon" | "CDS" | "start_codon" | "stop_codon") {
+                continue;
+            }
+
+            let id = attribute(fields[8], "transcript_id")
+                .or_else(|| attribute(fields[8], "Parent").and_then(|x| x.split(',').next()))
+                .ok_or(ParseError::Invalid(
+                    "GTF/GFF feature without transcript_id or Parent",
+                ))?;
+
+            // 1-based closed to 0-based half-open
+            let start =
+                parse_u64(fields[3]).map_err(|_| ParseError::NumberParse("feature start"))?;
+            let end = parse_u64(fields[4]).map_err(|_| ParseError::NumberParse("feature end"))?;
+            if start == 0 || start > end {
+                return Err(ParseError::Invalid("Feature start is not within 1..=end"));
+            }
+            let (start, end) = (start - 1, end);
+
+            let strand = fields[6].chars().next().unwrap_or('.');
+            if strand != '+' && strand != '-' {
+                return Err(ParseError::BadStrand(strand));
+            }
+
+            let tx = transcripts.entry(id).or_insert_with(|| {
+                order.push(id);
+                GtfTranscript {
+                    chrom: fields[0].to_string(),
+                    strand,
+                    ..Default::default()
+                }
+            });
+            if tx.chrom != fields[0] || tx.strand != strand {
+                return Err(ParseError::Invalid(
+                    "Transcript features span chromosomes or strands",
+                ));
+            }
+
+            match feature {
+                "exon" => tx.exons.push((start, end)),
+                _ => {
+                    tx.cds = Some(match tx.cds {
+                        Some((s, e)) => (s.min(start), e.max(end)),
+                        None => (start, end),
+                    })
+                }
+            }
+        }
+
+        let mut bed = String::new();
+        for id in order {
+            let mut tx = transcripts.remove(id).unwrap_or_default();
+            if tx.exons.is_empty() {
+                log::warn!("{} has no exon features, skipping it", id);
+                continue;
+            }
+            tx.exons.sort_unstable();
+
+            let start = tx.exons[0].0;
+            let end = tx.exons.iter().map(|x| x.1).max().unwrap_or(start);
+            let (cds_start, cds_end) = tx.cds.unwrap_or((start, start));
+
+            let sizes = tx
+                .exons
+                .iter()
+                .map(|(s, e)| format!("{},", e - s))
+                .collect::<String>();
+            let starts = tx
+                .exons
+                .iter()
+                .map(|(s, _)| format!("{},", s - start))
+                .collect::<String>();
+
+            bed.push_str(&format!(
+                "{}\t{}\t{}\t{}\t0\t{}\t{}\t{}\t0,0,0\t{}\t{}\t{}\n",
+                tx.chrom,
+                start,
+                end,
+                id,
+                tx.strand,
+                cds_start,
+                cds_end,
+                tx.exons.len(),
+                sizes,
+                starts
+            ));
+        }
+
+        Ok(bed)
+    }
+
+    pub fn parse(contents: &str, cds_overlap: bool) -> Result<Vec<GenePred>, ParseError> {
+        Self::to_bed12(contents)?
+            .lines()
+            .map(|x| Bed12::parse(x, cds_overlap))
+            .collect()
+    }
+}
+
+impl GenePredParser {
+    // name chrom strand txStart txEnd cdsStart cdsEnd exonCount exonStarts
+    // exonEnds [score ...] into the BED12 line of the same transcript; the
+    // leading bin column of UCSC table dumps is skipped
+    pub fn to_bed12(line: &str) -> Result<String, ParseError> {
+        let mut fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() > 10 && !matches!(fields[2], "+" | "-") && matches!(fields[3], "+" | "-") {
+            fields.remove(0);
+        }
+        if fields.len() < 10 {
+            return Err(ParseError::Invalid(
+                "genePred line has fewer than 10 columns",
+            ));
+        }
+
+        let group = |field: &str, name| -> Result<Vec<u64>, ParseError> {
+            field
+                .split(',')
+                .filter(|x| !x.is_empty())
+                .map(|x| parse_u64(x).map_err(|_| ParseError::NumberParse(name)))
+                .collect()
+        };
+
+        let tx_start = parse_u64(fields[3]).map_err(|_| ParseError::NumberParse("txStart"))?;
+        let starts = group(fields[8], "exonStarts")?;
+        let ends = group(fields[9], "exonEnds")?;
+        if starts.len() != ends.len() {
+            return Err(ParseError::LengthMismatch);
+        }
+        if starts
+            .iter()
+            .zip(&ends)
+            .any(|(s, e)| s > e || *s < tx_start)
+        {
+            return Err(ParseError::Invalid(
+                "Exon ends before it starts or before txStart",
+            ));
+        }
+
+        let sizes = starts
+            .iter()
+            .zip(&ends)
+            .map(|(s, e)| format!("{},", e - s))
+            .collect::<String>();
+        let offsets = starts
+            .iter()
+            .map(|s| format!("{},", s - tx_start))
+            .collect::<String>();
+
+        // genePredExt carries a score in column 11
+        let score = fields
+            .get(10)
+            .filter(|x| parse_u64(x).is_ok())
+            .unwrap_or(&"0");
+
+        Ok(format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t0,0,0\t{}\t{}\t{}",
+            fields[1],
+            fields[3],
+            fields[4],
+            fields[0],
+            score,
+            fields[2],
+            fields[5],
+            fields[6],
+            starts.len(),
+            sizes,
+            offsets
+        ))
+    }
+
+    pub fn parse(line: &str, cds_overlap: bool) -> Result<GenePred, ParseError> {
+        Bed12::parse(&Self::to_bed12(line)?, cds_overlap)
+    }
+}
+
+impl Bed6 {
+    // chrom start end name score strand into a single-exon BED12 line with
+    // an empty CDS at start; columns past the 6th are dropped. Unstranded
+    // records (strand .) are packed and written as +
+    pub fn to_bed12(line: &str) -> Result<String, ParseError> {
+        let fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() < 6 {
+            return Err(ParseError::Invalid("BED6 line has fewer than 6 columns"));
+        }
+
+        let start = parse_u64(fields[1]).map_err(|_| ParseError::NumberParse("start"))?;
+        let end = parse_u64(fields[2]).map_err(|_| ParseError::NumberParse("end"))?;
+        if end < start {
+            return Err(ParseError::Invalid("BED6 interval ends before it starts"));
+        }
+        let strand = match fields[5] {
+            "." => "+",
+            strand => strand,
+        };
+
+        Ok(format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t0,0,0\t1\t{},\t0,",
+            fields[0],
+            start,
+            end,
+            fields[3],
+            fields[4],
+            strand,
+            start,
+            start,
+            end - start
+        ))
+    }
+
+    pub fn parse(line: &str, cds_overlap: bool) -> Result<GenePred, ParseError> {
+        Bed12::parse(&Self::to_bed12(line)?, cds_overlap)
+    }
+}
+
+// shifts chromStart and thickStart of a BED line by delta; blockStarts are
+// relative to chromStart, so absolute block starts move along with it
+pub fn shift_starts(line: &str, delta: i64) -> Option<String> {
+    let mut fields = line.split('\t').map(String::from).collect::<Vec<_>>();
+    for idx in [1, 6] {
+        let pos = fields.get(idx)?.parse::<i64>().ok()? + delta;
+        if pos < 0 {
+            return None;
+        }
+        fields[idx] = pos.to_string();
+    }
+
+    Some(fields.join("\t"))
+}
+
+// unsigned decimal parser for the coordinate hot loop: digits only, no
+// sign or whitespace, overflow is an error
 #[inline(always)]
-fn get_coords(
+pub fn parse_u64(field: &str) -> Result<u64, &'static str> {
+    let bytes = field.as_bytes();
+    if bytes.is_empty() {
+        return Err("Empty number");
+    }
+
+    let mut value: u64 = 0;
+    for &b in bytes {
+        let digit = b.wrapping_sub(b'0');
+        if digit > 9 {
+            return Err("Non-digit in number");
+        }
+        value = value
+            .checked_mul(10)
+            .and_then(|x| x.checked_add(digit as u64))
+            .ok_or("Number out of range")?;
+    }
+
+    Ok(value)
+}
+
+#[inline(always)]
+fn has_duplicated_blocks(starts: &str, sizes: &str) -> bool {
+    let blocks = starts
+        .split(',')
+        .filter(|x| !x.is_empty())
+        .zip(sizes.split(',').filter(|x| !x.is_empty()))
+        .collect::<Vec<_>>();
+
+    let unique = blocks.iter().collect::<HashSet<_>>().len();
+    blocks.len() != unique
+}
+
+// BED12 spec: the first block starts at chromStart, the last one ends at
+// chromEnd and none goes past it
+fn check_block_bounds(
     starts: &str,
     sizes: &str,
     tx_start: u64,
     tx_end: u64,
+) -> Result<(), ParseError> {
+    let span = tx_end
+        .checked_sub(tx_start)
+        .ok_or(ParseError::BlockOutOfBounds)?;
+
+    let (mut first, mut last) = (u64::MAX, 0);
+    for (start, size) in starts
+        .split(',')
+        .filter(|x| !x.is_empty())
+        .zip(sizes.split(',').filter(|x| !x.is_empty()))
+    {
+        let start = parse_u64(start).map_err(|_| ParseError::NumberParse("exon_starts"))?;
+        let size = parse_u64(size).map_err(|_| ParseError::NumberParse("exon_sizes"))?;
+        let end = start
+            .checked_add(size)
+            .ok_or(ParseError::OutOfRange("exon_starts"))?;
+
+        if end > span {
+            return Err(ParseError::BlockOutOfBounds);
+        }
+        (first, last) = (first.min(start), last.max(end));
+    }
+
+    if first != 0 || last != span {
+        return Err(ParseError::BlockOutOfBounds);
+    }
+
+    Ok(())
+}
+
+type Coords = (HashSet<(u64, u64)>, HashSet<(u64, u64)>);
+
+#[inline(always)]
+fn get_coords(
+    starts: &str,
+    sizes: &str,
+    tx_start: u64,
     cds_start: u64,
     cds_end: u64,
     strand: char,
     cds_overlap: bool,
-) -> Result<(HashSet<(u64, u64)>, HashSet<(u64, u64)>), &'static str> {
-    let group = |field: &str| -> Result<Vec<u64>, &'static str> {
+) -> Result<Coords, ParseError> {
+    let group = |field: &str, name| -> Result<Vec<u64>, ParseError> {
         field
             .split(',')
-            .filter_map(|num| {
-                if !num.is_empty() {
-                    Some(num.parse::<u64>().expect("Cannot parse number"))
-                } else {
-                    None
-                }
-            })
-            .map(|num| Ok(num))
+            .filter(|num| !num.is_empty())
+            .map(|num| parse_u64(num).map_err(|_| ParseError::NumberParse(name)))
             .collect()
     };
 
-    let ss = group(starts)?;
-    let sz = group(sizes)?;
+    let ss = group(starts, "exon_starts")?;
+    let sz = group(sizes, "exon_sizes")?;
 
     if ss.len() != sz.len() {
-        return Err("Exon start and end vectors have different lengths");
+        return Err(ParseError::LengthMismatch);
     }
 
-    let offset = match strand {
-        '+' => tx_start,
-        '-' => tx_end,
-        _ => return Err("Strand is not + or -"),
-    };
+    if strand != '+' && strand != '-' {
+        return Err(ParseError::BadStrand(strand));
+    }
 
-    let exons = ss
+    // blockStarts are relative to chromStart on both strands
+    let blocks = ss
         .iter()
         .zip(&sz)
-        .map(|(&s, &z)| match strand {
-            '+' => {
-                if cds_overlap {
-                    if s + z + offset < cds_start || s + offset > cds_end {
-                        return Err("UTRs are not allowed in CDS exons");
-                    } else if s + offset < cds_start {
-                        if s + z + offset > cds_end {
-                            return Ok((cds_start, cds_end));
-                        }
-                        return Ok((cds_start, s + z + offset));
-                    } else if s + z + offset > cds_end {
-                        return Ok((s + offset, cds_end));
-                    }
-                }
+        .map(|(&s, &z)| {
+            let start = tx_start.checked_add(s);
+            let end = start.and_then(|x| x.checked_add(z));
+            start.zip(end).ok_or(ParseError::OutOfRange("exon_starts"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-                Ok((s + offset, s + z + offset))
+    let exons = blocks
+        .into_iter()
+        .map(|(start, end)| {
+            if cds_overlap {
+                if end < cds_start || start > cds_end {
+                    return Err(ParseError::UtrInCds);
+                }
+                return Ok((start.max(cds_start), end.min(cds_end)));
             }
-            '-' => {
-                if cds_overlap {
-                    if offset - s < cds_start || offset - s - z > cds_end {
-                        return Err("UTRs are not allowed in CDS exons");
-                    } else if offset - s - z < cds_start {
-                        if offset - s > cds_end {
-                            return Ok((cds_start, cds_end));
-                        }
-                        return Ok((cds_start, offset - s));
-                    } else if offset - s > cds_end {
-                        return Ok((offset - s - z, cds_end));
-                    }
-                };
 
-                Ok((offset - s - z, offset - s))
-            }
-            _ => return Err("Strand is not + or -"),
+            Ok((start, end))
         })
         .filter_map(Result::ok)
         .collect::<HashSet<_>>();
@@ -230,47 +848,30 @@ fn abs_pos(
     cds_start: &str,
     cds_end: &str,
     strand: char,
-    get: impl Fn(&str) -> Result<u64, &'static str>,
-) -> Result<(u64, u64, u64, u64), &'static str> {
+    get: impl Fn(&str, &'static str) -> Result<u64, ParseError>,
+) -> Result<(u64, u64, u64, u64), ParseError> {
     match strand {
-        '+' => {
-            let tx_start = get(tx_start)?;
-            let tx_end = get(tx_end)?;
-            let cds_start = get(cds_start)?;
-            let cds_end = get(cds_end)?;
-
-            Ok((tx_start, tx_end, cds_start, cds_end))
-        }
-        '-' => {
-            let tx_start = get(tx_start)?;
-            let tx_end = get(tx_end)?;
-            let cds_start = get(cds_start)?;
-            let cds_end = get(cds_end)?;
-
-            Ok((
-                SCALE - tx_end,
-                SCALE - tx_start,
-                SCALE - cds_end,
-                SCALE - cds_start,
-            ))
-        }
-        _ => Err("Strand is not + or -"),
+        '+' | '-' => Ok((
+            get(tx_start, "tx_start")?,
+            get(tx_end, "tx_end")?,
+            get(cds_start, "cds_start")?,
+            get(cds_end, "cds_end")?,
+        )),
+        _ => Err(ParseError::BadStrand(strand)),
     }
 }
 
 #[inline(always)]
 fn gapper(intervals: &HashSet<(u64, u64)>) -> HashSet<(u64, u64)> {
     let mut vintervals: Vec<(u64, u64)> = intervals.iter().copied().collect();
-    vintervals.sort_by(|a, b| a.0.cmp(&b.0));
+    vintervals.sort_by_key(|a| a.0);
 
     let mut gaps = HashSet::with_capacity(vintervals.len());
     for window in vintervals.windows(2) {
         if let [prev, next] = window {
-            let gap_start = prev.1 + 1;
-            let gap_end = next.0 - 1;
-
-            if gap_start < gap_end {
-                gaps.insert((gap_start, gap_end));
+            // half-open like the exons: the intron is [prev.1, next.0)
+            if prev.1 < next.0 {
+                gaps.insert((prev.1, next.0));
             }
         }
     }
@@ -282,6 +883,281 @@ fn gapper(intervals: &HashSet<(u64, u64)>) -> HashSet<(u64, u64)> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_bed6_single_exon() {
+        let record = Bed6::parse("s8\t100\t250\tpeak1\t17\t-", false).unwrap();
+
+        assert_eq!((record.start, record.end), (100, 250));
+        assert_eq!((record.cds_start, record.cds_end), (100, 100));
+        assert_eq!(record.exon_count, 1);
+        assert_eq!(record.exons, vec![(100, 250)]);
+        assert!(record.introns.is_empty());
+        assert_eq!(record.strand, '-');
+        assert_eq!(record.score(), Some(17.0));
+
+        assert!(Bed6::parse("s8\t100\t250\tpeak1\t0", false).is_err());
+
+        let unstranded = Bed6::parse("s8\t100\t250\tpeak2\t0\t.", false).unwrap();
+        assert_eq!(unstranded.strand, '+');
+        assert_eq!(unstranded.exons, vec![(100, 250)]);
+    }
+
+    #[test]
+    fn test_colorline_pads_short_lines() {
+        let mut record = Bed12::parse(
+            "s8\t100\t200\ttx1\t0\t+\t110\t190\t0,0,0\t1\t100,\t0,",
+            false,
+        )
+        .unwrap();
+        record.line = "s8\t100\t200\ttx1\t5\t+".to_string();
+
+        let record = record.colorline("255,0,0");
+        assert_eq!(record.line, "s8\t100\t200\ttx1\t5\t+\t100\t200\t255,0,0");
+        assert_eq!(record.rgb, "255,0,0");
+    }
+
+    #[test]
+    fn test_colorline_only_touches_column_9() {
+        let record = Bed12::parse(
+            "s8\t100\t400\ttx1\t7\t+\t110\t390\t0,0,0\t2\t100,100,\t0,200,\textra1",
+            false,
+        )
+        .unwrap();
+        let colored = record.clone().colorline("0,128,255");
+
+        assert_eq!(
+            colored.line,
+            "s8\t100\t400\ttx1\t7\t+\t110\t390\t0,128,255\t2\t100,100,\t0,200,\textra1"
+        );
+        assert_eq!(colored.rgb, "0,128,255");
+        assert_eq!(
+            GenePred {
+                line: record.line.clone(),
+                rgb: record.rgb.clone(),
+                ..colored
+            },
+            record
+        );
+    }
+
+    #[test]
+    fn test_genepred_matches_bed12() {
+        let bed = [
+            "s8\t100\t200\ttx1\t0\t+\t110\t190\t0,0,0\t2\t20,20,\t0,80,",
+            "s8\t300\t600\ttx2\t0\t-\t330\t550\t0,0,0\t2\t50,100,\t0,200,",
+        ];
+        let genepred = [
+            "tx1\ts8\t+\t100\t200\t110\t190\t2\t100,180,\t120,200,",
+            // genePredExt with a leading bin column
+            "585\ttx2\ts8\t-\t300\t600\t330\t550\t2\t300,500,\t350,600,\t0\tg2\tcmpl\tcmpl\t0,0,",
+        ];
+
+        for (bed, genepred) in bed.iter().zip(genepred) {
+            assert_eq!(GenePredParser::to_bed12(genepred).unwrap(), *bed);
+            assert_eq!(
+                GenePredParser::parse(genepred, true).unwrap(),
+                Bed12::parse(bed, true).unwrap()
+            );
+        }
+
+        assert!(
+            GenePredParser::parse("tx1\ts8\t+\t100\t200\t110\t190\t2\t100,180,\t120,", false)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_bed12_reader_yields_errors_lazily() {
+        let input = "# header\n\
+            s8\t100\t200\tread1\t0\t+\t110\t190\t0\t2\t20,20,\t0,80,\n\
+            s8\t100\t200\tread2\t0\t*\t110\t190\t0\t1\t100,\t0,\r\n\
+            \n\
+            s9\t300\t600\tread3\t0\t-\t330\t550\t0\t2\t50,100,\t0,200,";
+
+        let records = Bed12Reader::new(std::io::Cursor::new(input), true).collect::<Vec<_>>();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].as_ref().unwrap().exons,
+            vec![(110, 120), (180, 190)]
+        );
+        assert_eq!(records[1], Err(ParseError::BadStrand('*')));
+        assert_eq!(records[2].as_ref().unwrap().name, "read3");
+        assert_eq!(
+            records[2].as_ref().unwrap().exons,
+            vec![(330, 350), (500, 550)]
+        );
+    }
+
+    #[test]
+    fn test_gtf_matches_bed12() {
+        let gtf = "# header\n\
+            s8\tsrc\texon\t101\t120\t.\t+\t.\tgene_id \"g1\"; transcript_id \"tx1\";\n\
+            s8\tsrc\tCDS\t111\t120\t.\t+\t0\tgene_id \"g1\"; transcript_id \"tx1\";\n\
+            s8\tsrc\texon\t181\t200\t.\t+\t.\tgene_id \"g1\"; transcript_id \"tx1\";\n\
+            s8\tsrc\tCDS\t181\t187\t.\t+\t2\tgene_id \"g1\"; transcript_id \"tx1\";\n\
+            s8\tsrc\tstop_codon\t188\t190\t.\t+\t0\tgene_id \"g1\"; transcript_id \"tx1\";\n\
+            s8\tsrc\texon\t501\t600\t.\t-\t.\tID=e1;Parent=tx2\n\
+            s8\tsrc\tCDS\t511\t550\t.\t-\t0\tID=c1;Parent=tx2\n\
+            s8\tsrc\texon\t301\t350\t.\t-\t.\tID=e2;Parent=tx2\n\
+            s8\tsrc\tCDS\t331\t350\t.\t-\t2\tID=c2;Parent=tx2\n";
+
+        let records = Gtf::parse(gtf, false).unwrap();
+        let expected = [
+            "s8\t100\t200\ttx1\t0\t+\t110\t190\t0,0,0\t2\t20,20,\t0,80,",
+            "s8\t300\t600\ttx2\t0\t-\t330\t550\t0,0,0\t2\t50,100,\t0,200,",
+        ]
+        .iter()
+        .map(|x| Bed12::parse(x, false).unwrap())
+        .collect::<Vec<_>>();
+
+        assert_eq!(records, expected);
+        assert_eq!(records[1].exons, vec![(300, 350), (500, 600)]);
+        assert_eq!((records[1].cds_start, records[1].cds_end), (330, 550));
+
+        let bad = "s8\tsrc\texon\t101\t120\t.\t+\t.\tgene_id \"g1\";\n";
+        assert!(Gtf::parse(bad, false).is_err());
+    }
+
+    #[test]
+    fn test_parse_u64_edges() {
+        assert_eq!(parse_u64("0"), Ok(0));
+        assert_eq!(parse_u64("000123"), Ok(123));
+        assert_eq!(parse_u64(&u64::MAX.to_string()), Ok(u64::MAX));
+        assert!(parse_u64("18446744073709551616").is_err());
+        assert!(parse_u64("").is_err());
+        assert!(parse_u64("12a").is_err());
+        assert!(parse_u64("-1").is_err());
+        assert!(parse_u64(" 1").is_err());
+        assert_eq!(
+            Bed12::parse("s8\t1x0\t200\tr\t0\t+\t100\t200\t0\t1\t100,\t0,", false),
+            Err(ParseError::NumberParse("tx_start"))
+        );
+        assert_eq!(
+            Bed12::parse("s8\t100\t200\tr\t0\t+\t100\t200\t0\t1\t1o0,\t0,", false),
+            Err(ParseError::NumberParse("exon_sizes"))
+        );
+    }
+
+    #[test]
+    fn test_parse_trailing_columns() {
+        let line = "s8\t100\t300\tread1\t0\t+\t100\t300\t0\t2\t50,50,\t0,150,";
+        let extended = format!("{}\tprotein_coding\tGENE1", line);
+
+        let record = Bed12::parse(line, false).unwrap();
+        assert!(record.extra.is_empty());
+
+        let annotated = Bed12::parse(&extended, false).unwrap();
+        assert_eq!(annotated.exons, record.exons);
+        assert_eq!(annotated.cds_end, record.cds_end);
+        assert_eq!(annotated.extra, vec!["protein_coding", "GENE1"]);
+        assert_eq!(annotated.line(), &extended);
+    }
+
+    #[test]
+    fn test_minus_strand_genomic_coordinates() {
+        let line = "s8\t300\t600\ttx2\t0\t-\t330\t550\t0\t2\t50,100,\t0,200,";
+        let record = Bed12::parse(line, false).unwrap();
+
+        assert_eq!((record.start, record.end), (300, 600));
+        assert_eq!((record.cds_start, record.cds_end), (330, 550));
+        assert_eq!(record.exons, vec![(300, 350), (500, 600)]);
+        assert_eq!(record.introns, vec![(350, 500)]);
+        assert_eq!(record.to_bed12(), line);
+
+        // past the 100Gb the reflected layout used to cap coordinates at
+        let far = 200_000_000_000u64;
+        let line = format!(
+            "s8\t{}\t{}\ttx3\t0\t-\t{}\t{}\t0\t1\t100,\t0,",
+            far,
+            far + 100,
+            far,
+            far + 100
+        );
+        assert_eq!(Bed12::parse(&line, false).unwrap().start, far);
+
+        let line = format!(
+            "s8\t{}\t{}\ttx4\t0\t-\t0\t0\t0\t1\t100,\t10,",
+            u64::MAX - 50,
+            u64::MAX
+        );
+        assert_eq!(
+            Bed12::parse(&line, false),
+            Err(ParseError::OutOfRange("exon_starts"))
+        );
+    }
+
+    #[test]
+    fn test_duplicated_blocks() {
+        let line = "s8\t100\t300\tread1\t0\t+\t100\t300\t0\t3\t50,50,50,\t0,0,150,";
+
+        let record = Bed12::parse(line, false).unwrap();
+        assert_eq!(record.exon_count, 2);
+        assert_eq!(record.exons, vec![(100, 150), (250, 300)]);
+
+        assert_eq!(
+            Bed12::parse_with(line, false, true),
+            Err(ParseError::DuplicatedBlocks)
+        );
+    }
+
+    #[test]
+    fn test_strict_block_bounds() {
+        let line = "s8\t100\t300\tread1\t0\t+\t100\t300\t0\t2\t50,50,\t0,150,";
+        assert!(Bed12::parse_with(line, false, true).is_ok());
+
+        // second block runs to 320, past tx_end
+        let line = "s8\t100\t300\tread1\t0\t+\t100\t300\t0\t2\t50,70,\t0,150,";
+        assert_eq!(
+            Bed12::parse_with(line, false, true),
+            Err(ParseError::BlockOutOfBounds)
+        );
+        assert!(Bed12::parse_with(line, false, false).is_ok());
+
+        // last block stops short of tx_end
+        let line = "s8\t100\t300\tread1\t0\t-\t100\t300\t0\t2\t50,40,\t0,150,";
+        assert_eq!(
+            Bed12::parse_with(line, false, true),
+            Err(ParseError::BlockOutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_split_at_introns_long_intron() {
+        let line =
+            "chr1\t1000\t1002000\ttx\t0\t+\t1050\t1001950\t0\t3\t100,100,100,\t0,500,1000900,";
+        let record = Bed12::parse(line, false).unwrap();
+
+        assert_eq!(record.clone().split_at_introns(2_000_000).len(), 1);
+
+        let fragments = record.split_at_introns(100_000);
+
+        assert_eq!(fragments.len(), 2);
+        assert_eq!(fragments[0].exons, vec![(1000, 1100), (1500, 1600)]);
+        assert_eq!(fragments[0].exon_count, 2);
+        assert_eq!((fragments[0].cds_start, fragments[0].cds_end), (1050, 1600));
+        assert_eq!(fragments[1].exons, vec![(1001900, 1002000)]);
+        assert!(fragments[1].introns.is_empty());
+
+        let reparsed = Bed12::parse(&fragments[1].line, false).unwrap();
+        assert_eq!(reparsed, fragments[1]);
+        assert_eq!(reparsed.name, "tx_2");
+    }
+
+    #[test]
+    fn test_split_at_introns_nested_block() {
+        let line =
+            "chr1\t1000\t1002000\ttx\t0\t+\t1050\t1001950\t0\t3\t4000,100,100,\t0,500,1000900,";
+        let record = Bed12::parse(line, false).unwrap();
+
+        let fragments = record.split_at_introns(100_000);
+
+        assert_eq!(fragments.len(), 2);
+        assert_eq!(fragments[0].exons, vec![(1000, 5000), (1500, 1600)]);
+        assert_eq!((fragments[0].start, fragments[0].end), (1000, 5000));
+        assert_eq!(fragments[1].exons, vec![(1001900, 1002000)]);
+    }
+
     #[test]
     fn test_bed12_abs_pos_plus() {
         let tx_start = "10";
@@ -291,7 +1167,7 @@ mod tests {
         let strand = '+';
 
         let (tx_start, tx_end, cds_start, cds_end) =
-            abs_pos(tx_start, tx_end, cds_start, cds_end, strand, |x| {
+            abs_pos(tx_start, tx_end, cds_start, cds_end, strand, |x, _| {
                 Ok(x.parse().unwrap())
             })
             .unwrap();
@@ -311,15 +1187,33 @@ mod tests {
         let strand = '-';
 
         let (tx_start, tx_end, cds_start, cds_end) =
-            abs_pos(tx_start, tx_end, cds_start, cds_end, strand, |x| {
+            abs_pos(tx_start, tx_end, cds_start, cds_end, strand, |x, _| {
                 Ok(x.parse().unwrap())
             })
             .unwrap();
 
-        assert_eq!(tx_start, SCALE - 20);
-        assert_eq!(tx_end, SCALE - 10);
-        assert_eq!(cds_start, SCALE - 20);
-        assert_eq!(cds_end, SCALE - 10);
+        assert_eq!(tx_start, 10);
+        assert_eq!(tx_end, 20);
+        assert_eq!(cds_start, 10);
+        assert_eq!(cds_end, 20);
+    }
+
+    #[test]
+    fn test_gapper_edge_coordinates() {
+        let sorted = |gaps: HashSet<(u64, u64)>| {
+            let mut gaps = gaps.into_iter().collect::<Vec<_>>();
+            gaps.sort_unstable();
+            gaps
+        };
+
+        let exons = [(0, 10), (10, 20), (30, 40)].into_iter().collect();
+        assert_eq!(sorted(gapper(&exons)), vec![(20, 30)]);
+
+        let exons = [(0, 0), (0, 5)].into_iter().collect();
+        assert!(gapper(&exons).is_empty());
+
+        let exons = [(0, 10), (u64::MAX - 1, u64::MAX)].into_iter().collect();
+        assert_eq!(sorted(gapper(&exons)), vec![(10, u64::MAX - 1)]);
     }
 
     #[test]
@@ -327,7 +1221,6 @@ mod tests {
         let start = "0,30";
         let size = "10,10";
         let tx_start = 10;
-        let tx_end = 50;
         let cds_start = 15;
         let cds_end = 45;
         let strand = '+';
@@ -337,7 +1230,6 @@ mod tests {
             start,
             size,
             tx_start,
-            tx_end,
             cds_start,
             cds_end,
             strand,
@@ -346,16 +1238,13 @@ mod tests {
         .unwrap();
 
         let mut exons = exons.iter().cloned().collect::<Vec<_>>();
-        exons.sort_unstable_by(|a, b| a.cmp(b));
+        exons.sort_unstable();
 
         let mut introns = introns.iter().cloned().collect::<Vec<_>>();
-        introns.sort_unstable_by(|a, b| a.cmp(b));
+        introns.sort_unstable();
 
-        assert_eq!(
-            exons,
-            [(15, 20), (40, 45)].iter().cloned().collect::<Vec<_>>()
-        );
-        assert_eq!(introns, [(21, 39)].iter().cloned().collect::<Vec<_>>());
+        assert_eq!(exons, [(15, 20), (40, 45)].to_vec());
+        assert_eq!(introns, [(20, 40)].to_vec());
     }
 
     #[test]
@@ -369,15 +1258,18 @@ mod tests {
         let strand = '-';
         let cds_overlap = true;
 
-        let get = |field: &str| field.parse::<u64>().map_err(|_| "Cannot parse field");
-        let (tx_start, tx_end, cds_start, cds_end) =
+        let get = |field: &str, name| {
+            field
+                .parse::<u64>()
+                .map_err(|_| ParseError::NumberParse(name))
+        };
+        let (tx_start, _, cds_start, cds_end) =
             abs_pos(tx_start, tx_end, cds_start, cds_end, strand, get).unwrap();
 
         let (exons, introns) = get_coords(
             start,
             size,
             tx_start,
-            tx_end,
             cds_start,
             cds_end,
             strand,
@@ -386,29 +1278,13 @@ mod tests {
         .unwrap();
 
         let mut exons = exons.iter().cloned().collect::<Vec<_>>();
-        exons.sort_unstable_by(|a, b| a.cmp(b));
+        exons.sort_unstable();
 
         let mut introns = introns.iter().cloned().collect::<Vec<_>>();
-        introns.sort_unstable_by(|a, b| a.cmp(b));
+        introns.sort_unstable();
 
-        assert_eq!(
-            exons,
-            [
-                (99999999920, 99999999930),
-                (99999999940, 99999999950),
-                (99999999960, 99999999970)
-            ]
-            .iter()
-            .cloned()
-            .collect::<Vec<_>>()
-        );
-        assert_eq!(
-            introns,
-            [(99999999931, 99999999939), (99999999951, 99999999959)]
-                .iter()
-                .cloned()
-                .collect::<Vec<_>>()
-        );
+        assert_eq!(exons, [(30, 40), (50, 60), (70, 80)].to_vec());
+        assert_eq!(introns, [(40, 50), (60, 70)].to_vec());
     }
 
     #[test]
@@ -416,7 +1292,6 @@ mod tests {
         let start = "0,20,40,60,80";
         let size = "10,10,10,10,10";
         let tx_start = 10;
-        let tx_end = 100;
         let cds_start = 15;
         let cds_end = 95;
         let strand = '+';
@@ -426,7 +1301,6 @@ mod tests {
             start,
             size,
             tx_start,
-            tx_end,
             cds_start,
             cds_end,
             strand,
@@ -435,25 +1309,16 @@ mod tests {
         .unwrap();
 
         let mut exons = exons.iter().cloned().collect::<Vec<_>>();
-        exons.sort_unstable_by(|a, b| a.cmp(b));
+        exons.sort_unstable();
 
         let mut introns = introns.iter().cloned().collect::<Vec<_>>();
-        introns.sort_unstable_by(|a, b| a.cmp(b));
+        introns.sort_unstable();
 
         assert_eq!(
             exons,
-            [(15, 20), (30, 40), (50, 60), (70, 80), (90, 95)]
-                .iter()
-                .cloned()
-                .collect::<Vec<_>>()
-        );
-        assert_eq!(
-            introns,
-            [(21, 29), (41, 49), (61, 69), (81, 89)]
-                .iter()
-                .cloned()
-                .collect::<Vec<_>>()
+            [(15, 20), (30, 40), (50, 60), (70, 80), (90, 95)].to_vec()
         );
+        assert_eq!(introns, [(20, 30), (40, 50), (60, 70), (80, 90)].to_vec());
     }
 
     #[test]
@@ -467,15 +1332,18 @@ mod tests {
         let strand = '-';
         let cds_overlap = true;
 
-        let get = |field: &str| field.parse::<u64>().map_err(|_| "Cannot parse field");
-        let (tx_start, tx_end, cds_start, cds_end) =
+        let get = |field: &str, name| {
+            field
+                .parse::<u64>()
+                .map_err(|_| ParseError::NumberParse(name))
+        };
+        let (tx_start, _, cds_start, cds_end) =
             abs_pos(tx_start, tx_end, cds_start, cds_end, strand, get).unwrap();
 
         let (exons, introns) = get_coords(
             start,
             size,
             tx_start,
-            tx_end,
             cds_start,
             cds_end,
             strand,
@@ -484,33 +1352,12 @@ mod tests {
         .unwrap();
 
         let mut exons = exons.iter().cloned().collect::<Vec<_>>();
-        exons.sort_unstable_by(|a, b| a.cmp(b));
+        exons.sort_unstable();
 
         let mut introns = introns.iter().cloned().collect::<Vec<_>>();
-        introns.sort_unstable_by(|a, b| a.cmp(b));
+        introns.sort_unstable();
 
-        assert_eq!(
-            exons,
-            [
-                (99999999925, 99999999930),
-                (99999999940, 99999999950),
-                (99999999960, 99999999970),
-                (99999999980, 99999999985)
-            ]
-            .iter()
-            .cloned()
-            .collect::<Vec<_>>()
-        );
-        assert_eq!(
-            introns,
-            [
-                (99999999931, 99999999939),
-                (99999999951, 99999999959),
-                (99999999971, 99999999979)
-            ]
-            .iter()
-            .cloned()
-            .collect::<Vec<_>>()
-        );
+        assert_eq!(exons, [(15, 20), (30, 40), (50, 60), (70, 75)].to_vec());
+        assert_eq!(introns, [(20, 30), (40, 50), (60, 70)].to_vec());
     }
 }
@@ -0,0 +1,115 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use hashbrown::HashMap;
+
+use crate::{component_span, reader, Buckets};
+
+pub type Regions = HashMap<String, Vec<(u64, u64, String)>>;
+
+// BED4 of named regions keyed by chromosome
+pub fn read_regions<P: AsRef<Path> + Debug>(
+    file: P,
+) -> Result<Regions, Box<dyn std::error::Error>> {
+    let mut regions = Regions::new();
+
+    for line in reader(file)?.lines() {
+        if line.is_empty() || line.starts_with('#') || line.starts_with("track") {
+            continue;
+        }
+
+        let fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() < 4 {
+            return Err(format!("ERROR: region line without a name: {}", line).into());
+        }
+        regions.entry(fields[0].to_string()).or_default().push((
+            fields[1].parse()?,
+            fields[2].parse()?,
+            fields[3].to_string(),
+        ));
+    }
+
+    Ok(regions)
+}
+
+// routes every component to the region holding its span midpoint, one
+// {region_name}.bed per region in output and unassigned.bed for the rest
+pub fn partition_by_regions<T: AsRef<Path> + Debug>(
+    buckets: &Buckets,
+    regions: &Regions,
+    output: T,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(&output)?;
+
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    let mut routed: HashMap<String, Vec<String>> = HashMap::new();
+    for chr in chroms {
+        let Some(comps) = buckets.get(&chr) else {
+            continue;
+        };
+        for comp in comps.iter().filter(|x| !x.is_empty()) {
+            let (start, end) = component_span(comp);
+            let mid = start + (end - start) / 2;
+
+            let region = regions
+                .get(&chr)
+                .and_then(|x| x.iter().find(|(s, e, _)| *s <= mid && mid < *e))
+                .map_or("unassigned", |(_, _, name)| name.as_str());
+            routed
+                .entry(region.to_string())
+                .or_default()
+                .extend(comp.iter().map(|x| x.line().clone()));
+        }
+    }
+
+    for (region, records) in routed {
+        let path = output.as_ref().join(format!("{}.bed", region));
+        let mut file = BufWriter::new(File::create(path)?);
+        for line in records {
+            writeln!(file, "{}", line)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packbed;
+    use tempfile::{NamedTempFile, TempDir};
+
+    #[test]
+    fn test_partition_by_regions_two_arms() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t5000\t5100\tread3\t0\t-\t5010\t5090\t0\t1\t100,\t0,\ns9\t100\t200\tread4\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let mut bins = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(bins, "s8\t0\t1000\ts8p\ns8\t1000\t10000\ts8q\n").unwrap();
+        let regions = read_regions(bins.path()).unwrap();
+
+        let dir = TempDir::new().unwrap();
+        let buckets = packbed(vec![file.path()], false, false, false).unwrap();
+        partition_by_regions(&buckets, &regions, dir.path()).unwrap();
+
+        let names = |region: &str| {
+            std::fs::read_to_string(dir.path().join(format!("{}.bed", region)))
+                .unwrap()
+                .lines()
+                .map(|x| x.split('\t').nth(3).unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("s8p"), vec!["read1", "read2"]);
+        assert_eq!(names("s8q"), vec!["read3"]);
+        assert_eq!(names("unassigned"), vec!["read4"]);
+    }
+}
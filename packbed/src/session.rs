@@ -0,0 +1,218 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use dashmap::DashMap;
+use hashbrown::HashMap;
+use rayon::prelude::*;
+
+use crate::{
+    buckerize, components, parse_record, parse_tracks, postprocess_tracks, read_chrom, reader,
+    sort_tracks, Buckets, GenePred, GenePredMap, PackConfig,
+};
+
+// incremental packing for callers that keep appending records: every
+// ingest re-groups only the chromosomes that received new records.
+// scale_score is whole-input by nature and is not applied here
+#[derive(Debug, Default)]
+pub struct PackSession {
+    config: PackConfig,
+    tracks: GenePredMap,
+    components: DashMap<String, Vec<Vec<GenePred>>>,
+    offset: u64,
+}
+
+impl PackSession {
+    pub fn new(config: PackConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    // parses new BED lines and re-packs the chromosomes they touch,
+    // returning those chromosomes sorted
+    pub fn ingest(&mut self, new_lines: &str) -> Vec<String> {
+        let mut batch = new_lines
+            .par_lines()
+            .filter(|x| !x.starts_with("#"))
+            .filter_map(|x| parse_record(x, &self.config))
+            .fold(HashMap::new, |mut acc: GenePredMap, record| {
+                acc.entry(record.chrom.clone()).or_default().push(record);
+                acc
+            })
+            .reduce(HashMap::new, |mut acc, map| {
+                for (k, v) in map {
+                    acc.entry(k).or_default().extend(v);
+                }
+                acc
+            });
+
+        let mut touched = batch.keys().cloned().collect::<Vec<_>>();
+        touched.sort_unstable();
+
+        for (chr, records) in batch.drain() {
+            self.tracks.entry(chr).or_default().extend(records);
+        }
+
+        let mut affected = touched
+            .iter()
+            .filter_map(|chr| self.tracks.remove_entry(chr))
+            .collect::<GenePredMap>();
+        sort_tracks(&mut affected);
+
+        affected.par_iter().for_each(|(chr, transcripts)| {
+            self.components
+                .insert(chr.clone(), components(transcripts.clone(), &self.config));
+        });
+        self.tracks.extend(affected);
+
+        touched
+    }
+
+    // reads whatever complete lines were appended to file since the last
+    // call and ingests them; a trailing partial line waits for the next one
+    pub fn tail<P: AsRef<Path> + Debug>(&mut self, file: P) -> Result<Vec<String>, anyhow::Error> {
+        let mut file = File::open(file)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+
+        let mut buffer = String::new();
+        file.read_to_string(&mut buffer)?;
+
+        let complete = match buffer.rfind('\n') {
+            Some(idx) => &buffer[..=idx],
+            None => return Ok(Vec::new()),
+        };
+        self.offset += complete.len() as u64;
+
+        Ok(self.ingest(complete))
+    }
+
+    pub fn current_components(&self) -> &DashMap<String, Vec<Vec<GenePred>>> {
+        &self.components
+    }
+}
+
+// batch counterpart of PackSession: files and records accumulate per
+// chromosome and are only grouped once, by finalize. Parsing follows the
+// config given to new, since overlap_mode Cds already clips exons at parse time
+#[derive(Debug, Default)]
+pub struct Packer {
+    config: PackConfig,
+    tracks: GenePredMap,
+}
+
+impl Packer {
+    pub fn new(config: PackConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_file<P: AsRef<Path> + Debug>(&mut self, path: P) -> Result<(), anyhow::Error> {
+        let contents = match &self.config.chrom {
+            Some(chrom) => read_chrom(&path, chrom),
+            None => reader(&path),
+        }
+        .map_err(|e| anyhow::anyhow!("{:?}: {}", path, e))?;
+
+        for (chr, records) in parse_tracks(&contents, &self.config)? {
+            self.tracks.entry(chr).or_default().extend(records);
+        }
+
+        Ok(())
+    }
+
+    pub fn add_record(&mut self, record: GenePred) {
+        self.tracks
+            .entry(record.chrom.clone())
+            .or_default()
+            .push(record);
+    }
+
+    // score scaling and duplicate names see every file added so far
+    pub fn finalize(mut self) -> Buckets {
+        sort_tracks(&mut self.tracks);
+        postprocess_tracks(&mut self.tracks, &self.config);
+        buckerize(self.tracks, &self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packbed_with;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_packer_matches_single_packbed() {
+        let mut first = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            first,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,\n"
+        )
+        .unwrap();
+        let mut second = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            second,
+            "s8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t500\t600\tread4\t0\t+\t510\t590\t0\t1\t100,\t0,\n"
+        )
+        .unwrap();
+
+        let config = PackConfig::new(false, false, false);
+        let mut packer = Packer::new(config.clone());
+        packer.add_file(first.path()).unwrap();
+        packer.add_file(second.path()).unwrap();
+        let packed = packer.finalize();
+
+        let expected = packbed_with(vec![first.path(), second.path()], &config).unwrap();
+
+        let names = |buckets: &Buckets, chr: &str| {
+            buckets
+                .get(chr)
+                .unwrap()
+                .iter()
+                .map(|x| x.iter().map(|tx| tx.name.clone()).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(packed.len(), expected.len());
+        assert_eq!(names(&packed, "s8"), names(&expected, "s8"));
+        assert_eq!(
+            names(&packed, "s8"),
+            vec![vec!["read1", "read2"], vec!["read4"]]
+        );
+        assert_eq!(names(&packed, "s9"), names(&expected, "s9"));
+    }
+
+    #[test]
+    fn test_session_two_batches() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t500\t600\tread2\t0\t+\t510\t590\t0\t1\t100,\t0,\ns9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t550"
+        )
+        .unwrap();
+
+        let mut session = PackSession::new(PackConfig::new(false, false, false));
+        assert_eq!(session.tail(file.path()).unwrap(), vec!["s8", "s9"]);
+        assert_eq!(session.current_components().get("s8").unwrap().len(), 2);
+
+        // finishes the partial line and bridges nothing, then joins read1
+        write!(
+            file,
+            "\t650\tread4\t0\t+\t560\t640\t0\t1\t100,\t0,\ns8\t150\t250\tread5\t0\t+\t160\t240\t0\t1\t100,\t0,\n"
+        )
+        .unwrap();
+        assert_eq!(session.tail(file.path()).unwrap(), vec!["s8"]);
+
+        let comps = session.current_components().get("s8").unwrap().clone();
+        let names = |i: usize| comps[i].iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(comps.len(), 2);
+        assert_eq!(names(0), vec!["read1", "read5"]);
+        assert_eq!(names(1), vec!["read2", "read4"]);
+        assert_eq!(session.current_components().get("s9").unwrap().len(), 1);
+    }
+}
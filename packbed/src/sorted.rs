@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+
+use rayon::prelude::*;
+
+use crate::{Buckets, GenePred};
+
+// chromosomes in lexical order, each with its records flattened out of the
+// components and sorted by genomic start/end
+pub(crate) fn sorted_chroms(contents: Buckets) -> Vec<(String, Vec<GenePred>)> {
+    let mut chroms = contents
+        .into_par_iter()
+        .map(|(chr, comps)| {
+            let mut records = comps.into_iter().flatten().collect::<Vec<_>>();
+            records.par_sort_unstable_by(|a, b| {
+                (a.start, a.end)
+                    .cmp(&(b.start, b.end))
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+            (chr, records)
+        })
+        .collect::<Vec<_>>();
+    chroms.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    chroms
+}
+
+pub fn sorted_bedwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    contents: Buckets,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    for (_, records) in sorted_chroms(contents) {
+        for tx in records {
+            writeln!(file, "{}", tx.line())?;
+        }
+    }
+
+    Ok(())
+}
+
+// workers format each chromosome into its own buffer while a single
+// consumer writes buffers as soon as every preceding chromosome is out
+pub fn par_sorted_bedwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    contents: Buckets,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+    let chroms = sorted_chroms(contents);
+    let (tx, rx) = mpsc::channel::<(usize, String)>();
+
+    std::thread::scope(|scope| -> Result<(), Box<dyn std::error::Error>> {
+        let consumer = scope.spawn(move || -> std::io::Result<()> {
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+
+            for (idx, buffer) in rx {
+                pending.insert(idx, buffer);
+                while let Some(buffer) = pending.remove(&next) {
+                    file.write_all(buffer.as_bytes())?;
+                    next += 1;
+                }
+            }
+
+            file.flush()
+        });
+
+        chroms
+            .into_par_iter()
+            .enumerate()
+            .for_each_with(tx, |tx, (idx, (_, records))| {
+                let mut buffer = String::new();
+                for record in records {
+                    buffer.push_str(record.line());
+                    buffer.push('\n');
+                }
+                // only fails if the consumer died, which is reported below
+                let _ = tx.send((idx, buffer));
+            });
+
+        consumer
+            .join()
+            .map_err(|_| "ERROR: writer thread panicked")??;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packbed;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_par_sorted_bedwriter_matches_serial() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s9\t500\t600\tread4\t0\t+\t510\t590\t0\t1\t100,\t0,\ns8\t300\t400\tread2\t0\t-\t310\t390\t0\t1\t100,\t0,\ns8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns10\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let serial = NamedTempFile::new().unwrap();
+        let parallel = NamedTempFile::new().unwrap();
+        sorted_bedwriter(
+            serial.path(),
+            packbed(vec![file.path()], false, false, false).unwrap(),
+        )
+        .unwrap();
+        par_sorted_bedwriter(
+            parallel.path(),
+            packbed(vec![file.path()], false, false, false).unwrap(),
+        )
+        .unwrap();
+
+        let serial = std::fs::read_to_string(serial.path()).unwrap();
+        let parallel = std::fs::read_to_string(parallel.path()).unwrap();
+
+        assert_eq!(serial, parallel);
+
+        let names = serial
+            .lines()
+            .map(|x| x.split('\t').nth(3).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["read3", "read1", "read2", "read4"]);
+    }
+}
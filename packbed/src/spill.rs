@@ -0,0 +1,160 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use dashmap::DashMap;
+use rayon::prelude::*;
+use rmp_serde::{decode, encode};
+use tempfile::{NamedTempFile, TempPath};
+
+use crate::{components, unpack, GenePred, PackConfig};
+
+// components of chromosomes above the spill threshold live in msgpack temp
+// files and are only decoded back when requested
+#[derive(Debug, Default)]
+pub struct SpilledComponents {
+    resident: DashMap<String, Vec<Vec<GenePred>>>,
+    spilled: DashMap<String, TempPath>,
+}
+
+impl SpilledComponents {
+    pub fn len(&self) -> usize {
+        self.resident.len() + self.spilled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_spilled(&self, chr: &str) -> bool {
+        self.spilled.contains_key(chr)
+    }
+
+    pub fn chroms(&self) -> Vec<String> {
+        let mut chroms = self
+            .resident
+            .iter()
+            .map(|x| x.key().clone())
+            .chain(self.spilled.iter().map(|x| x.key().clone()))
+            .collect::<Vec<_>>();
+        chroms.sort_unstable();
+
+        chroms
+    }
+
+    pub fn get(&self, chr: &str) -> Result<Option<Vec<Vec<GenePred>>>, anyhow::Error> {
+        if let Some(comps) = self.resident.get(chr) {
+            return Ok(Some(comps.clone()));
+        }
+
+        match self.spilled.get(chr) {
+            Some(path) => {
+                let file = BufReader::new(File::open(path.value())?);
+                Ok(Some(decode::from_read(file)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn into_components(self) -> Result<DashMap<String, Vec<Vec<GenePred>>>, anyhow::Error> {
+        for (chr, path) in self.spilled {
+            let file = BufReader::new(File::open(&path)?);
+            self.resident.insert(chr, decode::from_read(file)?);
+        }
+
+        Ok(self.resident)
+    }
+}
+
+pub fn packbed_spill<T: AsRef<Path> + Debug + Send + Sync>(
+    bed: Vec<T>,
+    config: &PackConfig,
+    spill_threshold: usize,
+) -> Result<SpilledComponents, anyhow::Error> {
+    let tracks = unpack(bed, config)?;
+    let contents = SpilledComponents::default();
+
+    tracks
+        .into_par_iter()
+        .try_for_each(|(chr, transcripts)| -> Result<(), anyhow::Error> {
+            let spill = transcripts.len() > spill_threshold;
+            let comps = components(transcripts, config);
+
+            if spill {
+                let mut file = BufWriter::new(NamedTempFile::new()?);
+                encode::write(&mut file, &comps)?;
+                let path = file.into_inner()?.into_temp_path();
+
+                contents.spilled.insert(chr, path);
+            } else {
+                contents.resident.insert(chr, comps);
+            }
+
+            Ok(())
+        })?;
+
+    Ok(contents)
+}
+
+// reads spilled chromosomes back one at a time while writing
+pub fn spillwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    contents: &SpilledComponents,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    for chr in contents.chroms() {
+        let components = contents.get(&chr)?.unwrap_or_default();
+        for component in components {
+            for tx in component {
+                writeln!(file, "{}", tx.line())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bedwriter, packbed_with};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_spilled_output_matches_in_memory() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t3\t20,20,20,\t0,30,60,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t2\t50,50,\t0,100,\ns9\t100\t200\tread3\t0\t+\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let config = PackConfig::new(false, true, false);
+        let spilled = packbed_spill(vec![file.path()], &config, 1).unwrap();
+
+        assert!(spilled.is_spilled("s8"));
+        assert!(!spilled.is_spilled("s9"));
+
+        let spill_out = NamedTempFile::new().unwrap();
+        spillwriter(spill_out.path(), &spilled).unwrap();
+
+        let mem_out = NamedTempFile::new().unwrap();
+        let buckets = packbed_with(vec![file.path()], &config).unwrap();
+        bedwriter(mem_out.path(), buckets).unwrap();
+
+        let lines = |path: &Path| {
+            let mut lines = std::fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            lines.sort();
+            lines
+        };
+
+        assert_eq!(lines(spill_out.path()), lines(mem_out.path()));
+        assert_eq!(spilled.into_components().unwrap().len(), 2);
+    }
+}
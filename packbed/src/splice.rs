@@ -0,0 +1,251 @@
+use std::fmt::{self, Debug, Display};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use hashbrown::{HashMap, HashSet};
+use rayon::prelude::*;
+
+use crate::{reader, Buckets, GenePredMap};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SpliceClass {
+    Canonical,
+    SemiCanonical,
+    NonCanonical,
+}
+
+impl Display for SpliceClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpliceClass::Canonical => write!(f, "canonical"),
+            SpliceClass::SemiCanonical => write!(f, "semi-canonical"),
+            SpliceClass::NonCanonical => write!(f, "non-canonical"),
+        }
+    }
+}
+
+// sequences keyed by the first word of each header, uppercased
+pub fn read_fasta<P: AsRef<Path> + Debug>(
+    file: P,
+) -> Result<HashMap<String, Vec<u8>>, Box<dyn std::error::Error>> {
+    let contents = reader(file)?;
+    let mut seqs = HashMap::new();
+    let mut current: Option<(String, Vec<u8>)> = None;
+
+    for line in contents.lines() {
+        if let Some(header) = line.strip_prefix('>') {
+            if let Some((name, seq)) = current.take() {
+                seqs.insert(name, seq);
+            }
+            let name = header.split_whitespace().next().unwrap_or_default();
+            current = Some((name.to_string(), Vec::new()));
+        } else if let Some((_, seq)) = current.as_mut() {
+            seq.extend(line.trim_end().bytes().map(|b| b.to_ascii_uppercase()));
+        }
+    }
+
+    if let Some((name, seq)) = current {
+        seqs.insert(name, seq);
+    }
+
+    Ok(seqs)
+}
+
+fn revcomp(dinuc: &[u8]) -> [u8; 2] {
+    let comp = |b: u8| match b {
+        b'A' => b'T',
+        b'T' => b'A',
+        b'C' => b'G',
+        b'G' => b'C',
+        _ => b'N',
+    };
+    [comp(dinuc[1]), comp(dinuc[0])]
+}
+
+// introns are half-open genomic intervals over seq; on the minus strand the
+// donor sits at the intron end and both dinucleotides are reverse complemented
+pub fn annotate_splice_sites(introns: &[(u64, u64)], strand: char, seq: &[u8]) -> Vec<SpliceClass> {
+    introns
+        .iter()
+        .map(|&(start, end)| {
+            let (start, end) = (start as usize, end as usize);
+            if end > seq.len() || end < start + 4 {
+                return SpliceClass::NonCanonical;
+            }
+
+            let (left, right) = (&seq[start..start + 2], &seq[end - 2..end]);
+            let (donor, acceptor) = match strand {
+                '-' => (revcomp(right), revcomp(left)),
+                _ => ([left[0], left[1]], [right[0], right[1]]),
+            };
+
+            match (&donor, &acceptor) {
+                (b"GT", b"AG") => SpliceClass::Canonical,
+                (b"GC", b"AG") | (b"AT", b"AC") => SpliceClass::SemiCanonical,
+                _ => SpliceClass::NonCanonical,
+            }
+        })
+        .collect()
+}
+
+// BED6 of every intron plus its splice class as a 7th column
+pub fn intronwriter<P: AsRef<Path> + Debug, Q: AsRef<Path> + Debug>(
+    file: P,
+    contents: &Buckets,
+    fasta: Q,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let seqs = read_fasta(fasta)?;
+    let mut file = BufWriter::new(File::create(file)?);
+
+    let mut chroms = contents.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    for chr in chroms {
+        let Some(seq) = seqs.get(&chr) else {
+            log::warn!("{} not found in FASTA, skipping its introns", chr);
+            continue;
+        };
+
+        let comps = contents
+            .get(&chr)
+            .map(|x| x.value().clone())
+            .unwrap_or_default();
+        let mut lines = comps
+            .par_iter()
+            .flatten()
+            .flat_map_iter(|tx| {
+                let classes = annotate_splice_sites(&tx.introns, tx.strand, seq);
+                tx.introns
+                    .iter()
+                    .zip(classes)
+                    .map(|(&(s, e), class)| {
+                        (
+                            s,
+                            e,
+                            format!(
+                                "{}\t{}\t{}\t{}\t0\t{}\t{}",
+                                chr, s, e, tx.name, tx.strand, class
+                            ),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        lines.par_sort_unstable();
+
+        for (_, _, line) in lines {
+            writeln!(file, "{}", line)?;
+        }
+    }
+
+    Ok(())
+}
+
+// distinct introns of tracks absent from reference on the same chromosome
+// and strand, as sorted genomic (start, end, strand)
+pub fn novel_introns(
+    tracks: &GenePredMap,
+    reference: &GenePredMap,
+) -> HashMap<String, Vec<(u64, u64, char)>> {
+    let introns = |map: &GenePredMap, chr: &str| -> HashSet<(u64, u64, char)> {
+        map.get(chr)
+            .map(|txs| {
+                txs.iter()
+                    .flat_map(|tx| tx.introns.iter().map(move |&(s, e)| (s, e, tx.strand)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    tracks
+        .par_iter()
+        .filter_map(|(chr, _)| {
+            let known = introns(reference, chr);
+            let mut novel = introns(tracks, chr)
+                .into_iter()
+                .filter(|x| !known.contains(x))
+                .collect::<Vec<_>>();
+            novel.sort_unstable();
+
+            (!novel.is_empty()).then(|| (chr.clone(), novel))
+        })
+        .collect()
+}
+
+pub fn novelintronwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    novel: &HashMap<String, Vec<(u64, u64, char)>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    let mut chroms = novel.keys().collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    for chr in chroms {
+        for (start, end, strand) in &novel[chr] {
+            writeln!(file, "{}\t{}\t{}\tnovel\t0\t{}", chr, start, end, strand)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bed12;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_annotate_splice_sites_both_strands() {
+        let mut fasta = NamedTempFile::with_suffix(".fa").unwrap();
+        write!(
+            fasta,
+            ">s8 plus\nAAAAAGTCC\nCCAGAAAAA\n>s9 minus\naaaaaCTGGGGACaaaaa\n"
+        )
+        .unwrap();
+        let seqs = read_fasta(fasta.path()).unwrap();
+
+        let plus = Bed12::parse("s8\t0\t18\tread1\t0\t+\t0\t18\t0\t2\t5,5,\t0,13,", false).unwrap();
+        let minus =
+            Bed12::parse("s9\t0\t18\tread2\t0\t-\t0\t18\t0\t2\t5,5,\t0,13,", false).unwrap();
+
+        assert_eq!(plus.introns, vec![(5, 13)]);
+        assert_eq!(minus.introns, vec![(5, 13)]);
+
+        assert_eq!(
+            annotate_splice_sites(&plus.introns, '+', &seqs["s8"]),
+            vec![SpliceClass::Canonical]
+        );
+        assert_eq!(
+            annotate_splice_sites(&minus.introns, '-', &seqs["s9"]),
+            vec![SpliceClass::Canonical]
+        );
+        assert_eq!(
+            annotate_splice_sites(&plus.introns, '-', &seqs["s8"]),
+            vec![SpliceClass::NonCanonical]
+        );
+    }
+
+    #[test]
+    fn test_novel_introns_one_known() {
+        let map = |lines: &[&str]| {
+            let mut map = GenePredMap::new();
+            for line in lines {
+                let record = Bed12::parse(line, false).unwrap();
+                map.entry(record.chrom.clone()).or_default().push(record);
+            }
+            map
+        };
+
+        let tracks = map(&["s8\t100\t500\tread1\t0\t+\t100\t500\t0\t3\t50,50,50,\t0,200,350,"]);
+        let reference = map(&[
+            "s8\t100\t350\tref1\t0\t+\t100\t350\t0\t2\t50,50,\t0,200,",
+            "s8\t200\t500\tref2\t0\t-\t200\t500\t0\t2\t100,50,\t0,250,",
+        ]);
+
+        let novel = novel_introns(&tracks, &reference);
+        assert_eq!(novel["s8"], vec![(350, 450, '+')]);
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::{component_span, exon_catalog, Buckets, GenePredMap};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LengthSummary {
+    pub label: String,
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub p5: f64,
+    pub p25: f64,
+    pub p75: f64,
+    pub p95: f64,
+    pub min: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExonStats {
+    pub chroms: Vec<LengthSummary>,
+    pub genome: LengthSummary,
+}
+
+// linear interpolation between closest ranks over sorted values
+fn percentile(sorted: &[u64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] as f64 + (sorted[hi] as f64 - sorted[lo] as f64) * (rank - lo as f64)
+}
+
+fn summarize(label: String, mut lengths: Vec<u64>) -> LengthSummary {
+    lengths.par_sort_unstable();
+
+    let count = lengths.len();
+    let mean = if count > 0 {
+        lengths.iter().sum::<u64>() as f64 / count as f64
+    } else {
+        0.0
+    };
+
+    LengthSummary {
+        label,
+        count,
+        mean,
+        median: percentile(&lengths, 50.0),
+        p5: percentile(&lengths, 5.0),
+        p25: percentile(&lengths, 25.0),
+        p75: percentile(&lengths, 75.0),
+        p95: percentile(&lengths, 95.0),
+        min: lengths.first().copied().unwrap_or(0),
+        max: lengths.last().copied().unwrap_or(0),
+    }
+}
+
+pub fn exon_length_stats(tracks: &GenePredMap) -> ExonStats {
+    let lengths = tracks
+        .par_iter()
+        .map(|(chr, transcripts)| {
+            let lengths = transcripts
+                .iter()
+                .flat_map(|x| x.exons.iter().map(|(s, e)| e - s))
+                .collect::<Vec<_>>();
+            (chr.clone(), lengths)
+        })
+        .collect::<Vec<_>>();
+
+    let genome = lengths
+        .iter()
+        .flat_map(|(_, x)| x.iter().copied())
+        .collect::<Vec<_>>();
+
+    let mut chroms = lengths
+        .into_par_iter()
+        .map(|(chr, x)| summarize(chr, x))
+        .collect::<Vec<_>>();
+    chroms.sort_by(|a, b| a.label.cmp(&b.label));
+
+    ExonStats {
+        chroms,
+        genome: summarize("genome".to_string(), genome),
+    }
+}
+
+pub fn exonstatswriter<P: AsRef<Path> + Debug>(
+    file: P,
+    stats: &ExonStats,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    writeln!(
+        file,
+        "chrom\tn_exons\tmean\tmedian\tp5\tp25\tp75\tp95\tmin\tmax"
+    )?;
+    for x in stats.chroms.iter().chain(std::iter::once(&stats.genome)) {
+        writeln!(
+            file,
+            "{}\t{}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{}\t{}",
+            x.label, x.count, x.mean, x.median, x.p5, x.p25, x.p75, x.p95, x.min, x.max
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ComponentStat {
+    pub chrom: String,
+    pub index: usize,
+    pub n_transcripts: usize,
+    pub span_start: u64,
+    pub span_end: u64,
+    // bases covered by the merged member exons, overlaps counted once
+    pub total_exonic_bp: u64,
+    // distinct member strands, "+", "-" or "+,-"
+    pub strands: String,
+}
+
+// one row per component, chromosomes in lexical order and indices as
+// produced by buckerize
+pub fn component_stats(contents: &Buckets) -> Vec<ComponentStat> {
+    let mut stats = contents
+        .par_iter()
+        .flat_map_iter(|x| {
+            let chr = x.key().clone();
+            x.value()
+                .iter()
+                .enumerate()
+                .filter(|(_, comp)| !comp.is_empty())
+                .map(|(index, comp)| {
+                    let (span_start, span_end) = component_span(comp);
+
+                    let mut strands = comp.iter().map(|tx| tx.strand).collect::<Vec<_>>();
+                    strands.sort_unstable();
+                    strands.dedup();
+
+                    ComponentStat {
+                        chrom: chr.clone(),
+                        index,
+                        n_transcripts: comp.len(),
+                        span_start,
+                        span_end,
+                        total_exonic_bp: exon_catalog(comp).iter().map(|(s, e)| e - s).sum(),
+                        strands: strands
+                            .iter()
+                            .map(|x| x.to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    stats.par_sort_unstable_by(|a, b| (&a.chrom, a.index).cmp(&(&b.chrom, b.index)));
+
+    stats
+}
+
+pub fn statswriter<P: AsRef<Path> + Debug>(
+    file: P,
+    stats: &[ComponentStat],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    writeln!(
+        file,
+        "chrom\tcomponent\tn_transcripts\tstart\tend\texonic_bp\tstrands"
+    )?;
+    for x in stats {
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            x.chrom,
+            x.index,
+            x.n_transcripts,
+            x.span_start,
+            x.span_end,
+            x.total_exonic_bp,
+            x.strands
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bed12;
+
+    #[test]
+    fn test_component_stats_merges_exons() {
+        let component = [
+            "s8\t100\t400\tread1\t0\t+\t100\t400\t0\t2\t100,50,\t0,250,",
+            "s8\t150\t500\tread2\t0\t+\t150\t500\t0\t2\t100,100,\t0,250,",
+        ]
+        .iter()
+        .map(|x| Bed12::parse(x, false).unwrap())
+        .collect::<Vec<_>>();
+
+        let buckets = Buckets::new();
+        buckets.insert("s8".to_string(), vec![component]);
+        let stats = component_stats(&buckets);
+
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].n_transcripts, 2);
+        assert_eq!((stats[0].span_start, stats[0].span_end), (100, 500));
+        // [100, 250) and [350, 500) once merged
+        assert_eq!(stats[0].total_exonic_bp, 300);
+        assert_eq!(stats[0].strands, "+");
+    }
+
+    #[test]
+    fn test_exon_length_stats_mean_median() {
+        let mut tracks = GenePredMap::new();
+        for line in [
+            "s8\t100\t300\tread1\t0\t+\t100\t300\t0\t2\t10,20,\t0,100,",
+            "s8\t500\t700\tread2\t0\t+\t500\t700\t0\t1\t30,\t0,",
+            "s9\t100\t200\tread3\t0\t-\t100\t200\t0\t1\t100,\t0,",
+        ] {
+            let record = Bed12::parse(line, false).unwrap();
+            tracks.entry(record.chrom.clone()).or_default().push(record);
+        }
+
+        let stats = exon_length_stats(&tracks);
+
+        assert_eq!(stats.chroms.len(), 2);
+        assert_eq!(stats.chroms[0].label, "s8");
+        assert_eq!(stats.chroms[0].mean, 20.0);
+        assert_eq!(stats.chroms[0].median, 20.0);
+        assert_eq!(stats.genome.count, 4);
+        assert_eq!(stats.genome.mean, 40.0);
+        assert_eq!(stats.genome.median, 25.0);
+        assert_eq!((stats.genome.min, stats.genome.max), (10, 100));
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+use hashbrown::HashMap;
+use memmap2::Mmap;
+use rayon::prelude::*;
+
+use crate::record::is_annotation;
+use crate::{
+    buckerize, is_stdin, packbed_with, parse_record, postprocess_tracks, sort_tracks,
+    try_parse_record, Buckets, GenePred, GenePredMap, OrderMode, PackConfig, MAX_LINE_LEN,
+};
+
+// same limits as check_line_length and check_strict, per line
+fn check_line(line: &str, config: &PackConfig) -> Result<(), anyhow::Error> {
+    let max = config.max_line_len.unwrap_or(MAX_LINE_LEN);
+    if line.len() > max {
+        anyhow::bail!(
+            "line is {} bytes long (limit {}), the file may be malformed or not newline-delimited",
+            line.len(),
+            max
+        );
+    }
+
+    if config.strict && !line.is_empty() {
+        if let Err(e) = try_parse_record(line, config) {
+            anyhow::bail!("{}: {}", e, line);
+        }
+    }
+
+    Ok(())
+}
+
+// offset is where the line starts in its file, it stands in for the line
+// number under OrderMode::Input since parallel splits cannot count lines
+fn parse_line(
+    mut acc: GenePredMap,
+    line: &str,
+    offset: usize,
+    config: &PackConfig,
+) -> Result<GenePredMap, anyhow::Error> {
+    let line = line.trim_end_matches('\r');
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(acc);
+    }
+
+    check_line(line, config)?;
+    if let Some(mut record) = parse_record(line, config) {
+        if config.order_mode == OrderMode::Input {
+            record.order = offset;
+        }
+        acc.entry(record.chrom.clone()).or_default().push(record);
+    }
+
+    Ok(acc)
+}
+
+fn merge(mut acc: GenePredMap, map: GenePredMap) -> GenePredMap {
+    for (k, v) in map {
+        acc.entry(k).or_default().extend(v);
+    }
+    acc
+}
+
+// plain files are split in parallel straight over the mapped bytes, gzip
+// files are decoded line by line; neither is held as one String. Also
+// returns the (decoded) byte length, to offset the next file's records
+fn stream_file<P: AsRef<Path> + Debug>(
+    file: P,
+    config: &PackConfig,
+) -> Result<(GenePredMap, usize), anyhow::Error> {
+    let handle = File::open(&file)?;
+    let mmap = unsafe { Mmap::map(&handle)? };
+
+    if file.as_ref().extension().is_some_and(|x| x == "gz") && mmap.starts_with(&[0x1f, 0x8b]) {
+        let mut acc = HashMap::new();
+        let mut offset = 0;
+        for line in BufReader::new(MultiGzDecoder::new(&mmap[..])).lines() {
+            let line = line?;
+            acc = parse_line(acc, &line, offset, config)?;
+            offset += line.len() + 1;
+        }
+        return Ok((acc, offset));
+    }
+
+    let base = mmap.as_ptr() as usize;
+    let tracks = mmap
+        .par_split(|&b| b == b'\n')
+        .try_fold(HashMap::new, |acc, line| {
+            let offset = line.as_ptr() as usize - base;
+            parse_line(acc, std::str::from_utf8(line)?, offset, config)
+        })
+        .try_reduce(HashMap::new, |acc, map| Ok(merge(acc, map)))?;
+
+    Ok((tracks, mmap.len()))
+}
+
+// packbed_with for inputs too large to read into memory first, peak memory
+// is the parsed records plus the mapped pages being parsed
+pub fn packbed_streaming<T: AsRef<Path> + Debug + Send + Sync>(
+    bed: Vec<T>,
+    config: &PackConfig,
+) -> Result<Buckets, anyhow::Error> {
+    // region queries go through the bgzf index instead, GTF/GFF3 has to be
+    // grouped by transcript before any line can be parsed and stdin cannot
+    // be mapped
+    if config.chrom.is_some() || bed.iter().any(|x| is_annotation(x) || is_stdin(x)) {
+        return packbed_with(bed, config);
+    }
+
+    let parsed = bed
+        .par_iter()
+        .map(|file| stream_file(file, config).map_err(|e| anyhow::anyhow!("{:?}: {}", file, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // input order runs across files in the order they were given
+    let mut tracks = HashMap::new();
+    let mut base = 0;
+    for (mut map, len) in parsed {
+        if config.order_mode == OrderMode::Input {
+            map.values_mut().flatten().for_each(|x| x.order += base);
+        }
+        base += len + 1;
+        tracks = merge(tracks, map);
+    }
+
+    sort_tracks(&mut tracks);
+    log::info!(
+        "parsed {} records",
+        tracks
+            .values()
+            .map(|x: &Vec<GenePred>| x.len())
+            .sum::<usize>()
+    );
+    postprocess_tracks(&mut tracks, config);
+
+    Ok(buckerize(tracks, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_streaming_matches_in_memory() {
+        let contents = "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\n#comment\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\r\ns8\t500\t600\tread3\t0\t-\t510\t590\t0\t1\t100,\t0,\ns9\t100\t200\tread4\t0\t+\t110\t190\t0\t1\t100,\t0,";
+
+        let mut plain = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(plain, "{}", contents).unwrap();
+
+        let gz = NamedTempFile::with_suffix(".bed.gz").unwrap();
+        let mut encoder = GzEncoder::new(gz.reopen().unwrap(), Compression::default());
+        write!(encoder, "{}", contents).unwrap();
+        encoder.finish().unwrap();
+
+        let config = PackConfig::new(false, true, false);
+        let sorted = |buckets: Buckets| {
+            let mut comps = buckets
+                .into_iter()
+                .flat_map(|(_, comps)| comps)
+                .map(|comp| {
+                    let mut names = comp.into_iter().map(|x| x.name).collect::<Vec<_>>();
+                    names.sort_unstable();
+                    names
+                })
+                .collect::<Vec<_>>();
+            comps.sort_unstable();
+            comps
+        };
+
+        let expected = sorted(packbed_with(vec![plain.path()], &config).unwrap());
+        assert_eq!(expected.len(), 3);
+        assert_eq!(
+            sorted(packbed_streaming(vec![plain.path()], &config).unwrap()),
+            expected
+        );
+        assert_eq!(
+            sorted(packbed_streaming(vec![gz.path()], &config).unwrap()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_streaming_keeps_input_order() {
+        let mut first = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            first,
+            "s8\t150\t300\tzz\t0\t+\t160\t290\t0\t1\t150,\t0,\ns8\t100\t200\taa\t0\t+\t110\t190\t0\t1\t100,\t0,\n"
+        )
+        .unwrap();
+
+        let second = NamedTempFile::with_suffix(".bed.gz").unwrap();
+        let mut encoder = GzEncoder::new(second.reopen().unwrap(), Compression::default());
+        writeln!(encoder, "s8\t120\t220\tmm\t0\t+\t130\t210\t0\t1\t100,\t0,").unwrap();
+        encoder.finish().unwrap();
+
+        let config = PackConfig {
+            order_mode: OrderMode::Input,
+            ..PackConfig::new(false, false, false)
+        };
+        let names = |buckets: Buckets| {
+            buckets.get("s8").unwrap()[0]
+                .iter()
+                .map(|x| x.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let bed = vec![first.path(), second.path()];
+        assert_eq!(
+            names(packbed_streaming(bed.clone(), &config).unwrap()),
+            vec!["zz", "aa", "mm"]
+        );
+        assert_eq!(
+            names(packbed_with(bed, &config).unwrap()),
+            vec!["zz", "aa", "mm"]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{Buckets, GenePred};
+
+// majority strand and the fraction of members on it, ties go to +
+pub fn strand_purity(component: &[GenePred]) -> (char, f64) {
+    if component.is_empty() {
+        return ('+', 0.0);
+    }
+
+    let plus = component.iter().filter(|x| x.strand == '+').count();
+    let minus = component.len() - plus;
+    let (strand, count) = if plus >= minus {
+        ('+', plus)
+    } else {
+        ('-', minus)
+    };
+
+    (strand, count as f64 / component.len() as f64)
+}
+
+// sum and max of member scores, unparseable scores count as 0
+pub fn component_expression(component: &[GenePred]) -> (f64, f64) {
+    component
+        .iter()
+        .map(|x| x.score().unwrap_or(0.0))
+        .fold((0.0, 0.0), |(sum, max), x| (sum + x, f64::max(max, x)))
+}
+
+// one row per component, chromosomes in lexical order
+pub fn component_table(buckets: &Buckets) -> String {
+    let mut chroms = buckets.iter().map(|x| x.key().clone()).collect::<Vec<_>>();
+    chroms.sort_unstable();
+
+    let mut table = String::from(
+        "chrom\tcomponent\ttranscripts\tstrand\tstrand_purity\tmixed_strand\texpression_sum\texpression_max\n",
+    );
+    for chr in chroms {
+        let Some(comps) = buckets.get(&chr) else {
+            continue;
+        };
+        for (i, comp) in comps.iter().enumerate() {
+            let (strand, purity) = strand_purity(comp);
+            let (sum, max) = component_expression(comp);
+            table.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{:.4}\t{}\t{}\t{}\n",
+                chr,
+                i,
+                comp.len(),
+                strand,
+                purity,
+                purity < 1.0,
+                sum,
+                max
+            ));
+        }
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bed12;
+
+    #[test]
+    fn test_strand_purity_majority() {
+        let component = [
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,",
+            "s8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,",
+            "s8\t120\t250\tread3\t0\t-\t130\t240\t0\t1\t130,\t0,",
+        ]
+        .iter()
+        .map(|x| Bed12::parse(x, false).unwrap())
+        .collect::<Vec<_>>();
+
+        let (strand, purity) = strand_purity(&component);
+        assert_eq!(strand, '+');
+        assert!((purity - 2.0 / 3.0).abs() < 1e-9);
+
+        let buckets = Buckets::new();
+        buckets.insert("s8".to_string(), vec![component]);
+        let table = component_table(&buckets);
+        assert_eq!(
+            table.lines().nth(1).unwrap(),
+            "s8\t0\t3\t+\t0.6667\ttrue\t0\t0"
+        );
+    }
+
+    #[test]
+    fn test_component_expression_sum_and_max() {
+        let component = [
+            "s8\t100\t200\tread1\t10\t+\t110\t190\t0\t1\t100,\t0,",
+            "s8\t150\t300\tread2\t30\t+\t160\t290\t0\t1\t150,\t0,",
+        ]
+        .iter()
+        .map(|x| Bed12::parse(x, false).unwrap())
+        .collect::<Vec<_>>();
+
+        assert_eq!(component_expression(&component), (40.0, 30.0));
+    }
+}
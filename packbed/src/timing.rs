@@ -0,0 +1,118 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use dashmap::DashMap;
+use rayon::prelude::*;
+
+use crate::{
+    check_component_count, components, parse_tracks_with_report, postprocess_tracks, read_inputs,
+    Buckets, PackConfig,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChromTiming {
+    pub chrom: String,
+    pub records: usize,
+    pub bucketize_ms: f64,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PackTiming {
+    // parsing runs over all inputs at once, so it is only timed as a whole
+    pub parse_ms: f64,
+    pub chroms: Vec<ChromTiming>,
+}
+
+// same pipeline as packbed_with; only parsing and per-chromosome
+// components are timed, reading and postprocessing are left out
+pub fn packbed_timed<T: AsRef<Path> + Debug + Send + Sync>(
+    bed: Vec<T>,
+    config: &PackConfig,
+) -> Result<(Buckets, PackTiming), anyhow::Error> {
+    let contents = read_inputs(bed, config)?;
+
+    let st = Instant::now();
+    let (mut tracks, report) = parse_tracks_with_report(&contents, config)?;
+    let parse_ms = st.elapsed().as_secs_f64() * 1000.0;
+    report.log();
+
+    postprocess_tracks(&mut tracks, config);
+
+    let cmap = DashMap::new();
+    let timings = DashMap::new();
+    tracks.into_par_iter().for_each(|(chr, transcripts)| {
+        let records = transcripts.len();
+
+        let st = Instant::now();
+        let comps = components(transcripts, config);
+        let bucketize = st.elapsed();
+        check_component_count(&chr, comps.len(), config);
+
+        timings.insert(
+            chr.clone(),
+            ChromTiming {
+                chrom: chr.clone(),
+                records,
+                bucketize_ms: bucketize.as_secs_f64() * 1000.0,
+            },
+        );
+        if !comps.is_empty() {
+            cmap.insert(chr, comps);
+        }
+    });
+
+    let mut chroms = timings.into_iter().map(|(_, v)| v).collect::<Vec<_>>();
+    chroms.sort_by(|a, b| a.chrom.cmp(&b.chrom));
+
+    Ok((cmap, PackTiming { parse_ms, chroms }))
+}
+
+// parse time goes in a leading comment, the rows are per chromosome
+pub fn timingwriter<P: AsRef<Path> + Debug>(
+    file: P,
+    timing: &PackTiming,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(file)?);
+
+    writeln!(file, "#parse_ms\t{:.3}", timing.parse_ms)?;
+    writeln!(file, "chrom\tn_records\tbucketize_ms")?;
+    for chrom in &timing.chroms {
+        writeln!(
+            file,
+            "{}\t{}\t{:.3}",
+            chrom.chrom, chrom.records, chrom.bucketize_ms
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_packbed_timed_rows_per_chrom() {
+        let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+        write!(
+            file,
+            "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\ns9\t100\t200\tread3\t0\t-\t110\t190\t0\t1\t100,\t0,"
+        )
+        .unwrap();
+
+        let (buckets, timing) = packbed_timed(vec![file.path()], &PackConfig::default()).unwrap();
+        let timings = &timing.chroms;
+
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].chrom, "s8");
+        assert_eq!(timings[0].records, 2);
+        assert_eq!(timings[1].chrom, "s9");
+        assert_eq!(timings[1].records, 1);
+        assert!(timing.parse_ms >= 0.0 && timings.iter().all(|x| x.bucketize_ms >= 0.0));
+    }
+}
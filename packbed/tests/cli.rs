@@ -0,0 +1,265 @@
+use std::io::Write;
+use std::process::Command;
+
+use tempfile::{NamedTempFile, TempDir};
+
+fn bed() -> NamedTempFile {
+    let mut file = NamedTempFile::with_suffix(".bed").unwrap();
+    write!(
+        file,
+        "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread2\t0\t+\t160\t290\t0\t1\t150,\t0,\n"
+    )
+    .unwrap();
+    file
+}
+
+fn run(bed: &NamedTempFile, flags: &[&str]) -> std::process::Output {
+    let dir = TempDir::new().unwrap();
+    Command::new(env!("CARGO_BIN_EXE_packbed"))
+        .arg("-b")
+        .arg(bed.path())
+        .arg("-o")
+        .arg(dir.path().join("out.bin"))
+        .args(["--type", "bin"])
+        .args(flags)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_quiet_clean_run_has_no_stderr() {
+    let bed = bed();
+
+    let output = run(&bed, &["--quiet"]);
+    assert!(output.status.success());
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run(&bed, &["--verbose"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("s8: 2 records in 1 components"));
+}
+
+#[test]
+fn test_warn_component_count_fires_on_singletons() {
+    let mut bed = NamedTempFile::with_suffix(".bed").unwrap();
+    for i in 0..5 {
+        writeln!(
+            bed,
+            "s8\t{}\t{}\tread{}\t0\t+\t{}\t{}\t0\t1\t100,\t0,",
+            i * 1000,
+            i * 1000 + 100,
+            i,
+            i * 1000,
+            i * 1000 + 100
+        )
+        .unwrap();
+    }
+
+    let output = run(&bed, &["--warn_component_count", "2"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("s8: 5 components exceed --warn_component_count 2"));
+
+    let output = run(&bed, &["--warn_component_count", "5"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("exceed"));
+}
+
+#[test]
+fn test_gtf_input_is_accepted() {
+    let mut gtf = NamedTempFile::with_suffix(".gtf").unwrap();
+    write!(
+        gtf,
+        "s8\tsrc\texon\t101\t200\t.\t+\t.\ttranscript_id \"tx1\";\ns8\tsrc\texon\t151\t300\t.\t+\t.\ttranscript_id \"tx2\";\n"
+    )
+    .unwrap();
+
+    let output = run(&gtf, &["--verbose"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("s8: 2 records in 1 components"));
+}
+
+#[test]
+fn test_genepred_input_with_any_extension() {
+    let mut gp = NamedTempFile::with_suffix(".genePred").unwrap();
+    write!(
+        gp,
+        "tx1\ts8\t+\t100\t200\t100\t100\t1\t100,\t200,\ntx2\ts8\t+\t150\t300\t150\t150\t1\t150,\t300,\n"
+    )
+    .unwrap();
+
+    let output = run(&gp, &["--verbose"]);
+    assert!(!output.status.success());
+
+    let output = run(&gp, &["--verbose", "--input-format", "genepred"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("s8: 2 records in 1 components"));
+}
+
+#[test]
+fn test_bed_from_stdin() {
+    let dir = TempDir::new().unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_packbed"))
+        .args(["-b", "-", "--type", "bin", "--verbose"])
+        .arg("-o")
+        .arg(dir.path().join("out.bin"))
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&std::fs::read(bed().path()).unwrap())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("s8: 2 records in 1 components"));
+}
+
+#[test]
+fn test_bed_to_stdout() {
+    let bed = bed();
+    let output = Command::new(env!("CARGO_BIN_EXE_packbed"))
+        .arg("-b")
+        .arg(bed.path())
+        .args(["-o", "-", "--colorize", "--sorted"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let names = stdout
+        .lines()
+        .map(|x| x.split('\t').nth(3).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["read1", "read2"]);
+}
+
+#[test]
+fn test_timing_keeps_postprocessing() {
+    let mut bed = NamedTempFile::with_suffix(".bed").unwrap();
+    write!(
+        bed,
+        "s8\t100\t200\tread1\t10\t+\t110\t190\t0\t1\t100,\t0,\ns8\t100\t200\tread2\t10\t+\t110\t190\t0\t1\t100,\t0,\ns8\t150\t300\tread3\t40\t+\t160\t290\t0\t1\t150,\t0,\ns9\t100\t200\tread4\t20\t-\t110\t190\t0\t1\t100,\t0,\n"
+    )
+    .unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let pack = |flags: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_packbed"))
+            .arg("-b")
+            .arg(bed.path())
+            .args([
+                "-o",
+                "-",
+                "--colorize",
+                "--sorted",
+                "--dedup",
+                "--scale-score",
+                "--color_seed",
+                "7",
+            ])
+            .args(flags)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let timing = dir.path().join("timing.tsv");
+    let plain = pack(&[]);
+    let timed = pack(&["--timing", timing.to_str().unwrap()]);
+
+    assert_eq!(plain.lines().count(), 3);
+    assert_eq!(plain, timed);
+    assert!(std::fs::read_to_string(timing)
+        .unwrap()
+        .starts_with("#parse_ms\t"));
+}
+
+#[test]
+fn test_collapse_and_gff_with_blockless_records() {
+    let mut bed = NamedTempFile::with_suffix(".bed").unwrap();
+    write!(
+        bed,
+        "s8\t100\t200\tread1\t0\t+\t110\t190\t0\t0\t\t\ns8\t500\t700\tread2\t0\t+\t510\t690\t0\t1\t200,\t0,\n"
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_packbed"))
+        .arg("-b")
+        .arg(bed.path())
+        .args(["-o", "-", "--colorize", "--sorted", "--collapse"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let blocks = stdout
+        .lines()
+        .map(|x| x.split('\t').skip(9).take(3).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>();
+    assert_eq!(blocks, vec!["1 100, 0,", "1 200, 0,"]);
+
+    let dir = TempDir::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_packbed"))
+        .arg("-b")
+        .arg(bed.path())
+        .arg("-o")
+        .arg(dir.path().join("out.gff3"))
+        .args(["--type", "gff"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_novel_introns_with_prefixed_reference() {
+    let mut bed = NamedTempFile::with_suffix(".bed").unwrap();
+    write!(
+        bed,
+        "8\t100\t300\tread1\t0\t+\t100\t300\t0\t2\t50,50,\t0,150,\n8\t100\t300\tread2\t0\t+\t100\t300\t0\t2\t80,50,\t0,150,\n"
+    )
+    .unwrap();
+    let mut reference = NamedTempFile::with_suffix(".bed").unwrap();
+    writeln!(
+        reference,
+        "chr8\t100\t300\tref1\t0\t+\t100\t300\t0\t2\t50,50,\t0,150,"
+    )
+    .unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let novel = dir.path().join("novel.bed");
+    let output = run(
+        &bed,
+        &[
+            "--strip_chrom_prefix",
+            "chr",
+            "--novel_introns",
+            novel.to_str().unwrap(),
+            "--reference",
+            reference.path().to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+
+    assert_eq!(
+        std::fs::read_to_string(novel).unwrap(),
+        "8\t180\t250\tnovel\t0\t+\n"
+    );
+}
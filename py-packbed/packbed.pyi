@@ -0,0 +1,56 @@
+from os import PathLike
+from typing import Dict, List, Literal, Optional, Tuple, Union
+
+Components = Dict[str, List[List["PyGenePred"]]]
+
+class PyGenePred:
+    @property
+    def name(self) -> str: ...
+    @property
+    def chrom(self) -> str: ...
+    @property
+    def strand(self) -> str: ...
+    @property
+    def start(self) -> int: ...
+    @property
+    def end(self) -> int: ...
+    @property
+    def cds_start(self) -> int: ...
+    @property
+    def cds_end(self) -> int: ...
+    @property
+    def exons(self) -> List[Tuple[int, int]]: ...
+    @property
+    def introns(self) -> List[Tuple[int, int]]: ...
+    @property
+    def exon_count(self) -> int: ...
+    @property
+    def rgb(self) -> str: ...
+    @property
+    def line(self) -> str: ...
+    @property
+    def extra(self) -> List[str]: ...
+
+def pack(
+    bed: List[str],
+    overlap_cds: bool = True,
+    overlap_exon: bool = False,
+    colorize: bool = True,
+    mode: Optional[Literal["boundary", "exon", "cds"]] = None,
+) -> Components: ...
+def binreader(path: Union[str, PathLike[str]]) -> Components: ...
+def to_component(
+    bed: List[str],
+    hint: Optional[List[Tuple[str, List[int]]]],
+    overlap_cds: Optional[bool] = True,
+    overlap_exon: Optional[bool] = False,
+    out: Optional[str] = None,
+    colorize: Optional[bool] = True,
+) -> None: ...
+def write_components(
+    contents: Components,
+    output: Optional[str] = "comps.bed",
+    subdirs: Optional[bool] = False,
+    out_type: Literal["bed", "comp"] = "bed",
+    tag_component: bool = False,
+) -> None: ...
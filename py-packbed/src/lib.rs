@@ -1,3 +1,6 @@
+// pyo3 0.22 macros expand PyResult returns into same-type `.into()` conversions
+#![allow(clippy::useless_conversion)]
+
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::{BufWriter, Write};
@@ -6,44 +9,53 @@ use std::sync::Arc;
 
 use dashmap::DashMap;
 use hashbrown::HashMap;
-use packbed::{get_component, packbed, GenePred};
+use packbed::{get_component, packbed_with, GenePred, OverlapMode, PackConfig};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
 use rayon::prelude::*;
-use rmp_serde::decode;
 use serde::{Deserialize, Serialize};
 
+/// Packs BED12 files into overlapping components per chromosome.
+///
+/// overlap_cds and overlap_exon pick the overlap mode, as in the CLI;
+/// mode ("boundary", "exon" or "cds") replaces both when given.
+///
+/// >>> comps = pack(["reads.bed"], overlap_cds=False, overlap_exon=True)
+/// >>> sorted(comps)
+/// ['chr1', 'chr2']
 #[pyfunction]
-#[pyo3(signature = (bed, overlap_cds=true,overlap_exon=false, colorize=true))]
-fn pack(
-    py: Python,
+#[pyo3(signature = (bed, overlap_cds=true, overlap_exon=false, colorize=true, mode=None))]
+fn pack<'py>(
+    py: Python<'py>,
     bed: PyObject,
     overlap_cds: bool,
     overlap_exon: bool,
     colorize: bool,
-) -> PyResult<Bound<'_, PyDict>> {
-    let bed = bed
-        .extract::<Vec<String>>(py)
-        .expect("ERROR: failed to extract bed files");
-    let buckets =
-        packbed(bed, overlap_cds, overlap_exon, colorize).expect("ERROR: failed to pack bed files");
+    mode: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let bed = bed.extract::<Vec<String>>(py)?;
+    let mode = match mode {
+        Some(mode) => mode.parse::<OverlapMode>().map_err(PyValueError::new_err)?,
+        None => OverlapMode::from_flags(overlap_cds, overlap_exon),
+    };
+    let buckets = packbed_with(bed, &PackConfig::with_mode(mode, colorize))
+        .map_err(|e| PyValueError::new_err(format!("failed to pack bed files: {}", e)))?;
 
     convert_map_to_pydict(py, buckets)
 }
 
 #[pyfunction]
-fn binreader(py: Python, path: PyObject) -> PyResult<Bound<'_, PyDict>> {
-    let f = File::open(
-        path.extract::<PathBuf>(py)
-            .expect("ERROR: failed to extract file path"),
-    )?;
-
-    let contents = decode::from_read(f).expect("ERROR: failed to decode file");
-    convert_map_to_pydict(py, contents)
+fn binreader(py: Python<'_>, path: PyObject) -> PyResult<Bound<'_, PyDict>> {
+    let path = path.extract::<PathBuf>(py)?;
+    let contents = packbed::binreader(&path)
+        .map_err(|e| PyValueError::new_err(format!("failed to read {}: {}", path.display(), e)))?;
+
+    convert_map_to_pydict(py, contents.into_iter().collect())
 }
 
 #[pyfunction]
-#[pyo3(signature = (bed, hint,overlap_cds=true, overlap_exon=false, out=None, colorize=true))]
+#[pyo3(signature = (bed, hint, overlap_cds=true, overlap_exon=false, out=None, colorize=true))]
 fn to_component(
     py: Python,
     bed: PyObject,
@@ -56,46 +68,48 @@ fn to_component(
     let hint = hint.extract::<Vec<(String, Vec<usize>)>>(py).ok();
     let bed = bed.extract::<Vec<String>>(py)?;
 
-    if let Some(out) = out {
-        get_component(
-            bed,
-            hint,
-            Some(out.extract::<String>(py)?),
-            overlap_cds,
-            overlap_exon,
-            colorize,
-        );
-    } else {
-        get_component(bed, hint, None, overlap_cds, overlap_exon, colorize);
-    }
+    let out = match out {
+        Some(out) => Some(out.extract::<String>(py)?),
+        None => None,
+    };
 
-    Ok(())
+    get_component(bed, hint, out, overlap_cds, overlap_exon, colorize)
+        .map_err(|e| PyValueError::new_err(e.to_string()))
 }
 
 #[pyfunction]
-#[pyo3(signature = (contents, output="comps.bed", subdirs=false, out_type="bed"))]
+#[pyo3(signature = (contents, output="comps.bed", subdirs=false, out_type="bed", tag_component=false))]
 fn write_components(
     py: Python,
     contents: PyObject,
     output: Option<&str>,
     subdirs: Option<bool>,
     out_type: &str,
+    tag_component: bool,
 ) -> PyResult<()> {
     let mut map: HashMap<String, Vec<Vec<Arc<PyGenePred>>>> = HashMap::new();
     let py_dict = contents.downcast_bound::<PyDict>(py)?;
-    let out_type = TypeChoice::from_str(out_type).expect("ERROR: invalid output type");
+    let out_type = TypeChoice::from_str(out_type).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "invalid out_type '{}', expected 'bed' or 'comp'",
+            out_type
+        ))
+    })?;
 
     for (chr, buckets) in py_dict.iter() {
         let chr = chr.extract::<String>()?;
         let buckets = buckets.extract::<Vec<PyObject>>()?;
         let mut new_buckets: Vec<Vec<Arc<PyGenePred>>> = Vec::with_capacity(buckets.len());
 
-        for bucket in buckets.iter() {
+        for (i, bucket) in buckets.iter().enumerate() {
             let bucket = bucket.downcast_bound::<PyList>(py)?;
             let mut new_bucket: Vec<Arc<PyGenePred>> = Vec::with_capacity(bucket.len());
 
             for py_gene_pred in bucket.iter() {
-                let gene_pred: PyGenePred = py_gene_pred.extract()?;
+                let mut gene_pred: PyGenePred = py_gene_pred.extract()?;
+                if tag_component {
+                    gene_pred.tag(&chr, i);
+                }
                 new_bucket.push(Arc::new(gene_pred));
             }
             new_buckets.push(new_bucket);
@@ -104,18 +118,16 @@ fn write_components(
         map.insert(chr, new_buckets);
     }
 
+    let output = output.unwrap_or("comps.bed");
     match out_type {
-        TypeChoice::Comp => {
-            let output = Path::new(output.unwrap().trim_end_matches(".bed"));
-            let _ = compwriter(map, output, subdirs.unwrap());
-        }
-        TypeChoice::Bed => {
-            let output = Path::new(output.unwrap_or("comps.bed"));
-            let _ = bedwriter(output, map);
-        }
+        TypeChoice::Comp => compwriter(
+            map,
+            Path::new(output.trim_end_matches(".bed")),
+            subdirs.unwrap_or(false),
+        ),
+        TypeChoice::Bed => bedwriter(Path::new(output), map),
     }
-
-    Ok(())
+    .map_err(|e| PyValueError::new_err(format!("failed to write {}: {}", output, e)))
 }
 
 enum TypeChoice {
@@ -142,7 +154,7 @@ pub fn bedwriter<P: AsRef<Path> + Debug>(
     for (_, components) in contents {
         for component in components {
             for tx in component {
-                writeln!(file, "{}", tx.line())?;
+                writeln!(file, "{}", tx.line)?;
             }
         }
     }
@@ -161,7 +173,9 @@ fn py_chromsize(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     Ok(())
 }
 
-#[pyclass]
+/// A packed BED12 record; coordinates are 0-based half-open and genomic on
+/// both strands.
+#[pyclass(get_all)]
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct PyGenePred {
     pub name: String,
@@ -171,30 +185,19 @@ pub struct PyGenePred {
     pub end: u64,
     pub cds_start: u64,
     pub cds_end: u64,
+    /// (start, end) blocks, clipped to the CDS when packed with overlap_cds
     pub exons: Vec<(u64, u64)>,
+    /// (start, end) gaps between consecutive exons
     pub introns: Vec<(u64, u64)>,
     pub exon_count: usize,
     pub rgb: String,
     pub line: String,
+    #[serde(default)]
+    pub extra: Vec<String>,
 }
 
 #[pymethods]
 impl PyGenePred {
-    #[getter]
-    pub fn line(&self) -> &String {
-        &self.line
-    }
-
-    #[getter]
-    pub fn name(&self) -> &String {
-        &self.name
-    }
-
-    #[getter]
-    pub fn rgb(&self) -> &String {
-        &self.rgb
-    }
-
     fn __repr__(&self) -> PyResult<String> {
         Ok(format!(
             "PyGenePred(name='{}', chrom='{}', strand='{}', start={}, end={}, cds_start={}, cds_end={}, exon_count={}, exons={:?}, introns={:?}, color={:?})",
@@ -213,6 +216,19 @@ impl PyGenePred {
     }
 }
 
+impl PyGenePred {
+    // same {name}|comp_{chr}_{i} renaming as packbed::tag_components
+    fn tag(&mut self, chr: &str, i: usize) {
+        self.name = format!("{}|comp_{}_{}", self.name, chr, i);
+
+        let mut fields = self.line.split('\t').collect::<Vec<_>>();
+        if fields.len() > 3 {
+            fields[3] = &self.name;
+            self.line = fields.join("\t");
+        }
+    }
+}
+
 impl From<GenePred> for PyGenePred {
     fn from(gp: GenePred) -> Self {
         PyGenePred {
@@ -228,12 +244,13 @@ impl From<GenePred> for PyGenePred {
             exon_count: gp.exon_count,
             rgb: gp.rgb,
             line: gp.line,
+            extra: gp.extra,
         }
     }
 }
 
 pub fn convert_map_to_pydict(
-    py: Python,
+    py: Python<'_>,
     map: DashMap<String, Vec<Vec<GenePred>>>,
 ) -> PyResult<Bound<'_, PyDict>> {
     let py_dict = PyDict::new_bound(py);
@@ -266,41 +283,41 @@ pub fn compwriter<T: AsRef<Path> + Debug + Sync>(
 ) -> Result<(), Box<dyn std::error::Error>> {
     std::fs::create_dir_all(&output)?;
 
-    contents.iter().par_bridge().for_each(|(chr, buckets)| {
-        buckets
-            .iter()
-            .enumerate()
-            .par_bridge()
-            .for_each(|(i, bucket)| {
-                let filename = if subdirs {
-                    std::fs::create_dir_all(format!(
-                        "{}/comp_{}_{}",
-                        output.as_ref().display(),
-                        chr,
-                        i
-                    ))
-                    .expect("ERROR: Could not create directory");
-
-                    format!(
-                        "{}/comp_{}_{}/{}_{}.bed",
-                        output.as_ref().display(),
-                        chr,
-                        i,
-                        chr,
-                        i
-                    )
-                } else {
-                    format!("{}/{}_{}.bed", output.as_ref().display(), chr, i)
-                };
-
-                let mut file =
-                    BufWriter::new(File::create(&filename).expect("ERROR: Could not create file"));
-
-                bucket.iter().for_each(|x| {
-                    writeln!(file, "{}", x.line()).unwrap();
-                });
-            });
-    });
+    contents
+        .iter()
+        .par_bridge()
+        .try_for_each(|(chr, buckets)| {
+            buckets.iter().enumerate().par_bridge().try_for_each(
+                |(i, bucket)| -> std::io::Result<()> {
+                    let filename = if subdirs {
+                        std::fs::create_dir_all(format!(
+                            "{}/comp_{}_{}",
+                            output.as_ref().display(),
+                            chr,
+                            i
+                        ))?;
+
+                        format!(
+                            "{}/comp_{}_{}/{}_{}.bed",
+                            output.as_ref().display(),
+                            chr,
+                            i,
+                            chr,
+                            i
+                        )
+                    } else {
+                        format!("{}/{}_{}.bed", output.as_ref().display(), chr, i)
+                    };
+
+                    let mut file = BufWriter::new(File::create(&filename)?);
+                    for x in bucket {
+                        writeln!(file, "{}", x.line)?;
+                    }
+
+                    file.flush()
+                },
+            )
+        })?;
 
     Ok(())
 }